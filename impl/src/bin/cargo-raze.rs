@@ -21,20 +21,29 @@ use std::{
 use anyhow::{anyhow, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 
+use cargo_lock::Lockfile;
 use cargo_metadata::Metadata;
 use docopt::Docopt;
 
 use cargo_raze::{
   checks,
-  metadata::{MetadataFetcher, RazeMetadata, RazeMetadataFetcher},
+  duplicates::{find_duplicate_versions, render_duplicates_report},
+  explain::{explain_crate, render_explanations},
+  lockfile_diff::{diff_lockfiles, render_lockfile_changelog},
+  metadata::{CrateUpdate, MetadataFetcher, RazeMetadata, RazeMetadataFetcher},
   planning::{BuildPlanner, BuildPlannerImpl, PlannedBuild},
-  rendering::FileOutputs,
-  rendering::{bazel::BazelRenderer, BuildRenderer, RenderDetails},
+  rendering::{bazel::BazelRenderer, json::JsonRenderer, BuildRenderer, RenderDetails},
+  rendering::{
+    cache::{RenderCache, RENDER_CACHE_NAME},
+    find_orphaned_files, find_stale_files, prune_orphaned_files, render_output_manifest,
+    FileOutputs, OUTPUT_MANIFEST_NAME,
+  },
   settings::RazeSettings,
-  settings::{load_settings, GenMode, SettingsMetadataFetcher},
+  settings::{load_settings, GenMode, SettingsMetadataFetcher, VersionResolution},
   util::{find_bazel_workspace_root, find_lockfile, PlatformDetails},
 };
 
+use semver::VersionReq;
 use serde::Deserialize;
 use url::Url;
 
@@ -57,6 +66,17 @@ struct Options {
   flag_output: Option<String>,
   flag_manifest_path: Option<String>,
   flag_generate_lockfile: Option<bool>,
+  flag_check: Option<bool>,
+  flag_json: Option<bool>,
+  flag_offline: Option<bool>,
+  cmd_explain: bool,
+  arg_crate: Option<String>,
+  flag_all_paths: Option<bool>,
+  cmd_duplicates: bool,
+  flag_max_duplicates: Option<usize>,
+  flag_lockfile_changelog: Option<String>,
+  flag_update: Vec<String>,
+  flag_version_resolution: Option<VersionResolution>,
 }
 
 const USAGE: &str = r#"
@@ -65,8 +85,15 @@ Generate BUILD files for your pre-vendored Cargo dependencies.
 Usage:
     cargo-raze (-h | --help)
     cargo-raze (-V | --version)
-    cargo-raze [--verbose] [--quiet] [--color=<WHEN>] [--dryrun] [--cargo-bin-path=<PATH>] 
-               [--manifest-path=<PATH>] [--output=<PATH>] [--generate-lockfile]
+    cargo-raze [--verbose] [--quiet] [--color=<WHEN>] [--dryrun] [--cargo-bin-path=<PATH>] [--manifest-path=<PATH>] [--output=<PATH>] [--generate-lockfile] [--lockfile-changelog=<PATH>] [--update=<SPEC>...] [--version-resolution=<MODE>] [--check] [--json] [--offline]
+    cargo-raze explain [--verbose] [--quiet] [--color=<WHEN>] [--cargo-bin-path=<PATH>] [--manifest-path=<PATH>] [--json] [--offline] [--all-paths] <crate>
+    cargo-raze duplicates [--verbose] [--quiet] [--color=<WHEN>] [--cargo-bin-path=<PATH>] [--manifest-path=<PATH>] [--json] [--offline] [--max-duplicates=<N>]
+
+Commands:
+    explain                             Print the shortest path from each workspace member to
+                                        <crate>, given as `name` or `name@version`, or every
+                                        path with --all-paths
+    duplicates                          Report crates present in more than one version
 
 Options:
     -h, --help                          Print this message
@@ -79,6 +106,14 @@ Options:
     --manifest-path=<PATH>              Path to the Cargo.toml file to generate BUILD files for
     --output=<PATH>                     Path to output the generated into.
     --generate-lockfile                 Force a new `Cargo.raze.lock` file to be generated
+    --lockfile-changelog=<PATH>         Write the changes to a newly generated lockfile to <PATH>
+    --update=<SPEC>                     Update only the crate `name` or `name@version` in the lockfile
+    --version-resolution=<MODE>         Resolve new lockfiles with: Maximal, Minimal, DirectMinimal
+    --check                             Verify generated files are up to date without writing them
+    --json                              Write the planned build as JSON instead of BUILD files
+    --offline                           Never access the network, failing if anything requires it
+    --all-paths                         List every path to the explained crate, up to 1000
+    --max-duplicates=<N>                Fail if more than N crates are present in more than one version
 "#;
 
 fn main() -> Result<()> {
@@ -89,14 +124,45 @@ fn main() -> Result<()> {
   let (local_metadata, settings) = load_raze_settings(&options)?;
 
   // Fetch metadata
-  let raze_metadata = fetch_raze_metadata(&options, &settings, &local_metadata)?;
+  let previous_lockfile = find_previous_lockfile(&settings, &local_metadata)?;
+  let raze_metadata = fetch_raze_metadata(
+    &options,
+    &settings,
+    &local_metadata,
+    previous_lockfile.as_deref(),
+  )?;
+
+  // Summarize the changes to a newly generated lockfile
+  if let (Some(previous_lockfile), Some(lockfile)) = (&previous_lockfile, &raze_metadata.lockfile) {
+    report_lockfile_changes(&options, previous_lockfile, lockfile)?;
+  }
+
+  // Report duplicated crates instead of planning a build
+  if options.cmd_duplicates {
+    return report_duplicates(&options, &raze_metadata);
+  }
 
   // Do Planning
   let planned_build = do_planning(&settings, &raze_metadata)?;
 
+  // Explain why a crate is part of the build instead of rendering it
+  if options.cmd_explain {
+    return explain(&options, &raze_metadata, &planned_build);
+  }
+
   // Render BUILD files
-  let (render_details, bazel_file_outputs) =
-    render_files(&settings, &raze_metadata, &planned_build, &local_metadata)?;
+  let (render_details, bazel_file_outputs) = render_files(
+    &options,
+    &settings,
+    &raze_metadata,
+    &planned_build,
+    &local_metadata,
+  )?;
+
+  // Verify BUILD files are up to date instead of writing them
+  if options.flag_check.unwrap_or(false) {
+    return check_files(&bazel_file_outputs, &render_details, &settings, &options);
+  }
 
   // Write BUILD files
   write_files(&bazel_file_outputs, &render_details, &settings, &options)?;
@@ -136,11 +202,19 @@ fn load_raze_settings(options: &Options) -> Result<(Metadata, RazeSettings)> {
   let metadata = fetch_local_metadata(options)?;
 
   // Parse settings with that metadata
-  let settings = match load_settings(&metadata) {
+  let mut settings = match load_settings(&metadata) {
     Ok(settings) => settings,
     Err(err) => return Err(anyhow!(err.to_string())),
   };
 
+  if options.flag_offline.unwrap_or(false) {
+    settings.offline = true;
+  }
+
+  if let Some(version_resolution) = options.flag_version_resolution {
+    settings.version_resolution = version_resolution;
+  }
+
   if options.flag_verbose.unwrap_or(false) {
     println!("Loaded override settings: {:#?}", settings);
   }
@@ -174,12 +248,29 @@ fn fetch_local_metadata(options: &Options) -> Result<Metadata> {
     .with_context(|| format!("Failed to fetch metadata for {}", working_directory))
 }
 
+/// Finds the lockfile a previous run of cargo-raze would have used or generated, if any.
+fn find_previous_lockfile(
+  settings: &RazeSettings,
+  local_metadata: &Metadata,
+) -> Result<Option<Utf8PathBuf>> {
+  let cargo_raze_working_dir = find_bazel_workspace_root(local_metadata.workspace_root.as_ref())
+    .unwrap_or(current_dir_utf8()?);
+
+  Ok(find_lockfile(
+    local_metadata.workspace_root.as_ref(),
+    cargo_raze_working_dir
+      .join(settings.workspace_path.trim_start_matches('/'))
+      .as_ref(),
+  ))
+}
+
 fn fetch_raze_metadata(
   options: &Options,
   settings: &RazeSettings,
   local_metadata: &Metadata,
+  previous_lockfile: Option<&Utf8Path>,
 ) -> Result<RazeMetadata> {
-  let metadata_fetcher: RazeMetadataFetcher = match options.flag_cargo_bin_path {
+  let mut metadata_fetcher: RazeMetadataFetcher = match options.flag_cargo_bin_path {
     Some(ref cargo_bin_path) => RazeMetadataFetcher::new(
       cargo_bin_path,
       Url::parse(&settings.registry)?,
@@ -192,26 +283,38 @@ fn fetch_raze_metadata(
   let cargo_raze_working_dir = find_bazel_workspace_root(local_metadata.workspace_root.as_ref())
     .unwrap_or(current_dir_utf8()?);
 
-  let binary_dep_info = if settings.genmode == GenMode::Remote {
-    Some(&settings.binary_deps)
-  } else {
-    None
-  };
-
-  let reused_lockfile = if !options.flag_generate_lockfile.unwrap_or(false) {
-    find_lockfile(
-      local_metadata.workspace_root.as_ref(),
+  // Crates which are already vendored are always reused, but missing ones are only vendored when
+  // files are written
+  let is_writing_files = !options.flag_check.unwrap_or(false)
+    && !options.flag_dryrun.unwrap_or(false)
+    && !options.cmd_explain
+    && !options.cmd_duplicates;
+  if settings.genmode == GenMode::Vendored {
+    metadata_fetcher.set_vendor_dir(
       cargo_raze_working_dir
         .join(settings.workspace_path.trim_start_matches('/'))
-        .as_ref(),
-    )
+        .join(&settings.vendor_dir),
+      is_writing_files,
+    );
+  }
+
+  metadata_fetcher.set_crate_updates(
+    options
+      .flag_update
+      .iter()
+      .map(|spec| spec.parse())
+      .collect::<Result<Vec<CrateUpdate>>>()?,
+  );
+
+  let reused_lockfile = if !options.flag_generate_lockfile.unwrap_or(false) {
+    previous_lockfile.map(Utf8Path::to_path_buf)
   } else {
     None
   };
 
   let raze_metadata = metadata_fetcher.fetch_metadata(
     local_metadata.workspace_root.as_ref(),
-    binary_dep_info,
+    Some(&settings.binary_deps),
     reused_lockfile,
   )?;
 
@@ -219,6 +322,37 @@ fn fetch_raze_metadata(
   Ok(raze_metadata)
 }
 
+fn report_lockfile_changes(
+  options: &Options,
+  previous_lockfile: &Utf8Path,
+  lockfile: &Lockfile,
+) -> Result<()> {
+  let previous = Lockfile::load(previous_lockfile)
+    .with_context(|| format!("Failed to load lockfile: {}", previous_lockfile))?;
+  let changes = diff_lockfiles(&previous, lockfile);
+  let changelog = render_lockfile_changelog(&changes);
+
+  match &options.flag_lockfile_changelog {
+    Some(path) if !options.flag_dryrun.unwrap_or(false) => write_to_file(
+      Utf8Path::new(path),
+      &changelog,
+      options.flag_verbose.unwrap_or(false),
+    ),
+    _ if options.flag_quiet.unwrap_or(false) => Ok(()),
+    // Unchanged lockfiles are only reported when a changelog was asked for
+    None if changes.is_empty() => Ok(()),
+    // JSON output is printed to stdout, so the summary must not be mixed into it
+    _ if options.flag_json.unwrap_or(false) => {
+      eprint!("{}", changelog);
+      Ok(())
+    }
+    _ => {
+      print!("{}", changelog);
+      Ok(())
+    }
+  }
+}
+
 fn do_planning(settings: &RazeSettings, metadata: &RazeMetadata) -> Result<PlannedBuild> {
   let platform_details = match &settings.target {
     Some(target) => Some(PlatformDetails::new_using_rustc(target)?),
@@ -228,7 +362,56 @@ fn do_planning(settings: &RazeSettings, metadata: &RazeMetadata) -> Result<Plann
   BuildPlannerImpl::new(metadata.clone(), settings.clone()).plan_build(platform_details)
 }
 
+fn explain(options: &Options, metadata: &RazeMetadata, planned_build: &PlannedBuild) -> Result<()> {
+  // UNWRAP: Docopt requires <crate> for the explain command
+  let spec = options.arg_crate.as_ref().unwrap();
+  let (name, version_req) = match spec.split_once('@') {
+    // A version is matched like a Cargo.toml `=` requirement, so `0.9` matches any `0.9.x`
+    Some((name, version)) => (
+      name,
+      Some(
+        VersionReq::parse(&format!("={}", version))
+          .with_context(|| format!("Invalid version in `{}`", spec))?,
+      ),
+    ),
+    None => (spec.as_str(), None),
+  };
+
+  let explanations = explain_crate(
+    metadata,
+    planned_build,
+    name,
+    version_req.as_ref(),
+    options.flag_all_paths.unwrap_or(false),
+  )?;
+  if options.flag_json.unwrap_or(false) {
+    println!("{}", serde_json::to_string_pretty(&explanations)?);
+  } else {
+    print!("{}", render_explanations(&explanations));
+  }
+  Ok(())
+}
+
+fn report_duplicates(options: &Options, metadata: &RazeMetadata) -> Result<()> {
+  let duplicates = find_duplicate_versions(metadata)?;
+  if options.flag_json.unwrap_or(false) {
+    println!("{}", serde_json::to_string_pretty(&duplicates)?);
+  } else {
+    print!("{}", render_duplicates_report(&duplicates));
+  }
+
+  match options.flag_max_duplicates {
+    Some(max_duplicates) if duplicates.len() > max_duplicates => Err(anyhow!(
+      "{} crates are present in more than one version, but at most {} are allowed",
+      duplicates.len(),
+      max_duplicates
+    )),
+    _ => Ok(()),
+  }
+}
+
 fn render_files(
+  options: &Options,
   settings: &RazeSettings,
   metadata: &RazeMetadata,
   planned_build: &PlannedBuild,
@@ -237,7 +420,6 @@ fn render_files(
   let cargo_raze_working_dir = find_bazel_workspace_root(local_metadata.workspace_root.as_ref())
     .unwrap_or(current_dir_utf8()?);
 
-  let mut bazel_renderer = BazelRenderer::new();
   let render_details = RenderDetails {
     cargo_root: metadata.cargo_workspace_root.clone(),
     path_prefix: Utf8PathBuf::from(&settings.workspace_path.trim_start_matches('/')),
@@ -247,17 +429,155 @@ fn render_files(
     rust_rules_workspace_name: settings.rust_rules_workspace_name.clone(),
     experimental_api: settings.experimental_api,
     render_package_aliases: settings.render_package_aliases,
+    render_module_extension: settings.render_module_extension,
+    license_report: settings.license_report,
+    render_license_targets: settings.render_license_targets,
+    sbom_formats: settings.sbom_formats.clone(),
+  };
+  let mut bazel_file_outputs = if options.flag_json.unwrap_or(false) {
+    render_with(
+      &mut JsonRenderer::new(),
+      settings,
+      &render_details,
+      planned_build,
+    )?
+  } else {
+    let mut bazel_renderer = BazelRenderer::new();
+    if settings.incremental_rendering {
+      // Checks always render every crate so modified BUILD files are detected
+      let render_cache = if options.flag_check.unwrap_or(false) {
+        RenderCache::new()
+      } else {
+        RenderCache::load(
+          &render_details
+            .bazel_root
+            .join(&render_details.path_prefix)
+            .join(RENDER_CACHE_NAME),
+        )
+      };
+      bazel_renderer = bazel_renderer.with_render_cache(render_cache);
+    }
+
+    let bazel_file_outputs = render_with(
+      &mut bazel_renderer,
+      settings,
+      &render_details,
+      planned_build,
+    )?;
+    if let Some(render_cache) = bazel_renderer.render_cache() {
+      // The summary goes to stderr so it never mixes with output printed to stdout
+      if !options.flag_check.unwrap_or(false) && !options.flag_quiet.unwrap_or(false) {
+        eprintln!(
+          "Regenerated {} crate(s), skipped {} unchanged crate(s)",
+          render_cache.regenerated, render_cache.skipped
+        );
+      }
+    }
+    bazel_file_outputs
   };
-  let bazel_file_outputs = match &settings.genmode {
-    GenMode::Vendored => bazel_renderer.render_planned_build(&render_details, planned_build)?,
-    GenMode::Remote => {
-      bazel_renderer.render_remote_planned_build(&render_details, planned_build)?
-    } /* exhaustive, we control the definition */
+
+  // Record the outputs so the next run can identify files which are no longer generated
+  if !bazel_file_outputs.is_empty() && !options.flag_json.unwrap_or(false) {
+    let manifest = render_output_manifest(
+      &bazel_file_outputs,
+      &output_manifest_path(&render_details),
+      &render_details.bazel_root,
+    );
+    bazel_file_outputs.push(manifest);
+  }
+
+  Ok((render_details, bazel_file_outputs))
+}
+
+fn render_with(
+  renderer: &mut dyn BuildRenderer,
+  settings: &RazeSettings,
+  render_details: &RenderDetails,
+  planned_build: &PlannedBuild,
+) -> Result<Vec<FileOutputs>> {
+  let file_outputs = match &settings.genmode {
+    GenMode::Vendored => renderer.render_planned_build(render_details, planned_build)?,
+    GenMode::Remote => renderer.render_remote_planned_build(render_details, planned_build)?, /* exhaustive, we control the definition */
     // There are no file outputs to produce if `genmode` is Unspecified
     GenMode::Unspecified => Vec::new(),
   };
 
-  Ok((render_details, bazel_file_outputs))
+  Ok(file_outputs)
+}
+
+/// Returns the path of the manifest of files written by cargo-raze
+fn output_manifest_path(render_details: &RenderDetails) -> Utf8PathBuf {
+  render_details
+    .bazel_root
+    .join(&render_details.path_prefix)
+    .join(OUTPUT_MANIFEST_NAME)
+}
+
+/// Locates the existing BUILD files in the "remote" directory, all of which are replaced on each run
+fn existing_remote_build_files(
+  render_details: &RenderDetails,
+  settings: &RazeSettings,
+) -> Result<Vec<Utf8PathBuf>> {
+  let mut build_files = Vec::new();
+  if settings.genmode != GenMode::Remote {
+    return Ok(build_files);
+  }
+
+  let remote_dir = render_details
+    .bazel_root
+    .join(&render_details.path_prefix)
+    .join("remote");
+  if remote_dir.exists() {
+    let build_glob = format!("{}/BUILD*.bazel", remote_dir);
+    for entry in glob::glob(&build_glob)? {
+      build_files.push(
+        Utf8PathBuf::from_path_buf(entry?)
+          .map_err(|_e| anyhow!("Invalid UTF-8 in remote BUILD file path."))?,
+      );
+    }
+  }
+
+  Ok(build_files)
+}
+
+/// Compares rendered files with those on disk, printing a diff for each one which is out of date.
+fn check_files(
+  bazel_file_outputs: &[FileOutputs],
+  render_details: &RenderDetails,
+  settings: &RazeSettings,
+  options: &Options,
+) -> Result<()> {
+  // A JSON export replaces no previously generated files
+  let mut orphaned_files = Vec::new();
+  if !options.flag_json.unwrap_or(false) {
+    orphaned_files.extend(existing_remote_build_files(render_details, settings)?);
+    orphaned_files.extend(find_orphaned_files(
+      bazel_file_outputs,
+      &output_manifest_path(render_details),
+      &render_details.bazel_root,
+    )?);
+  }
+  let stale_files = find_stale_files(
+    bazel_file_outputs,
+    &orphaned_files,
+    &render_details.bazel_root,
+  )?;
+  if stale_files.is_empty() {
+    return Ok(());
+  }
+
+  for stale_file in stale_files.iter() {
+    print!("{}", stale_file.diff);
+  }
+
+  Err(anyhow!(
+    "{} generated file(s) are out of date. Please re-run cargo-raze: {:?}",
+    stale_files.len(),
+    stale_files
+      .iter()
+      .map(|stale_file| stale_file.path.as_str())
+      .collect::<Vec<&str>>()
+  ))
 }
 
 fn write_files(
@@ -266,18 +586,18 @@ fn write_files(
   settings: &RazeSettings,
   options: &Options,
 ) -> Result<()> {
-  if settings.genmode == GenMode::Remote {
-    let remote_dir = render_details
-      .bazel_root
-      .join(&render_details.path_prefix)
-      .join("remote");
+  if !options.flag_json.unwrap_or(false) {
     // Clean out the "remote" directory so users can easily see what build files are relevant
-    if remote_dir.exists() {
-      let build_glob = format!("{}/BUILD*.bazel", remote_dir);
-      for entry in glob::glob(&build_glob)? {
-        fs::remove_file(entry?)?;
+    for build_file in existing_remote_build_files(render_details, settings)? {
+      if !bazel_file_outputs
+        .iter()
+        .any(|output| output.path == build_file)
+      {
+        fs::remove_file(build_file)?;
       }
     }
+
+    prune_stale_outputs(bazel_file_outputs, render_details, settings, options)?;
   }
 
   for output in bazel_file_outputs.iter() {
@@ -285,6 +605,13 @@ fn write_files(
       println!("{}:\n{}", output.path, output.contents);
       continue;
     }
+    // Leave unchanged files untouched so their modification times are preserved
+    if fs::read_to_string(&output.path).ok().as_deref() == Some(output.contents.as_str()) {
+      if options.flag_verbose.unwrap_or(false) {
+        println!("{} is up to date", output.path);
+      }
+      continue;
+    }
     // Ensure all parent directories exist
     if let Some(parent) = &output.path.parent() {
       fs::create_dir_all(parent)?
@@ -299,6 +626,58 @@ fn write_files(
   Ok(())
 }
 
+/// Deletes, or reports, files written by a previous run of cargo-raze which are no longer generated
+fn prune_stale_outputs(
+  bazel_file_outputs: &[FileOutputs],
+  render_details: &RenderDetails,
+  settings: &RazeSettings,
+  options: &Options,
+) -> Result<()> {
+  let orphaned_files = find_orphaned_files(
+    bazel_file_outputs,
+    &output_manifest_path(render_details),
+    &render_details.bazel_root,
+  )?;
+  if orphaned_files.is_empty() {
+    return Ok(());
+  }
+
+  if options.flag_dryrun.unwrap_or(false) {
+    eprintln!(
+      "WARNING: The following files were generated by a previous run of cargo-raze but are no \
+       longer used, and would be deleted: {:?}",
+      orphaned_files
+    );
+    return Ok(());
+  }
+  if !settings.prune_stale_outputs {
+    eprintln!(
+      "WARNING: The following files were generated by a previous run of cargo-raze but are no \
+       longer used: {:?}. Remove `prune_stale_outputs = false` to delete them.",
+      orphaned_files
+    );
+    return Ok(());
+  }
+
+  let vendor_dir = match settings.genmode {
+    GenMode::Vendored => Some(
+      render_details
+        .bazel_root
+        .join(&render_details.path_prefix)
+        .join(&settings.vendor_dir),
+    ),
+    _ => None,
+  };
+  let removed = prune_orphaned_files(&orphaned_files, bazel_file_outputs, vendor_dir.as_deref())?;
+  if options.flag_verbose.unwrap_or(false) {
+    for path in removed.iter() {
+      println!("Removed stale output {}", path);
+    }
+  }
+
+  Ok(())
+}
+
 /// Writes rendered files to filesystem.
 fn write_to_file(path: &Utf8Path, contents: &str, verbose: bool) -> Result<()> {
   File::create(path).and_then(|mut f| f.write_all(contents.as_bytes()))?;
@@ -307,3 +686,46 @@ fn write_to_file(path: &Utf8Path, contents: &str, verbose: bool) -> Result<()> {
   }
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_options(args: &[&str]) -> Result<Options, docopt::Error> {
+    Docopt::new(USAGE)
+      .and_then(|d| d.argv(args.iter().copied()).parse())
+      .and_then(|d| d.deserialize())
+  }
+
+  #[test]
+  fn usage_accepts_flags_in_any_combination() {
+    let options = parse_options(&[
+      "cargo-raze",
+      "--dryrun",
+      "--output=out",
+      "--offline",
+      "--update=log",
+      "--update=libc@0.2.1",
+      "--version-resolution=Minimal",
+    ])
+    .unwrap();
+    assert_eq!(options.flag_dryrun, Some(true));
+    assert_eq!(options.flag_output.as_deref(), Some("out"));
+    assert_eq!(options.flag_offline, Some(true));
+    assert_eq!(options.flag_update, vec!["log", "libc@0.2.1"]);
+
+    let options = parse_options(&["cargo-raze", "explain", "--offline", "--json", "log"]).unwrap();
+    assert!(options.cmd_explain);
+    assert_eq!(options.arg_crate.as_deref(), Some("log"));
+    assert!(!options.flag_all_paths.unwrap_or(false));
+
+    let options = parse_options(&["cargo-raze", "explain", "log", "--all-paths"]).unwrap();
+    assert_eq!(options.flag_all_paths, Some(true));
+
+    let options = parse_options(&["cargo-raze", "duplicates", "--max-duplicates=2"]).unwrap();
+    assert!(options.cmd_duplicates);
+    assert_eq!(options.flag_max_duplicates, Some(2));
+
+    assert!(parse_options(&["cargo-raze", "explain", "--dryrun", "log"]).is_err());
+  }
+}
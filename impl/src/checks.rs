@@ -23,7 +23,9 @@ use camino::{Utf8Path, Utf8PathBuf};
 use crate::{
   error::RazeError,
   metadata::RazeMetadata,
-  settings::{CrateSettingsPerVersion, GenMode, RazeSettings},
+  planning::license::find_license_policy_violation,
+  registry::{self, RegistryIndex},
+  settings::{CrateSettingsPerVersion, GenMode, LicensePolicy, RazeSettings},
   util::collect_up_to,
   util::package_ident,
 };
@@ -43,6 +45,10 @@ pub fn check_metadata(
   // Check for errors
   check_resolve_matches_packages(&raze_metadata.metadata)?;
 
+  if settings.offline {
+    check_registry_indexes_are_local(&raze_metadata.metadata)?;
+  }
+
   if settings.genmode == GenMode::Vendored {
     check_all_vendored(&raze_metadata.metadata, settings, bazel_workspace_root)?;
   }
@@ -57,6 +63,8 @@ pub fn check_metadata(
   // Check for unused crate settings
   warn_unused_settings(&settings.crates, &raze_metadata.metadata.packages);
 
+  check_license_policy(&raze_metadata.metadata, &settings.license_policy)?;
+
   Ok(())
 }
 
@@ -70,7 +78,7 @@ fn check_lockfile_for_missing_checksums(
     // Filter out workspace members
     .filter(|pkg| !metadata.workspace_members.contains(&pkg.id))
     // Filter out non crates.io sources
-    .filter(|pkg| pkg.source.as_ref().map_or(false, |src| src.is_crates_io()))
+    .filter(|pkg| pkg.source.as_ref().map_or(false, registry::is_crates_io))
     // Filter for missing checksums and save the package identifier
     .filter_map(|pkg| {
       let package_ident = package_ident(&pkg.name, &pkg.version.to_string());
@@ -107,10 +115,13 @@ fn check_all_vendored(
   settings: &RazeSettings,
   bazel_workspace_root: &Utf8Path,
 ) -> Result<()> {
+  // Binary dependencies are workspace members, but are built from the vendor directory
   let non_workspace_packages: Vec<&Package> = metadata
     .packages
     .iter()
-    .filter(|pkg| !metadata.workspace_members.contains(&pkg.id))
+    .filter(|pkg| {
+      !metadata.workspace_members.contains(&pkg.id) || settings.binary_deps.contains_key(&pkg.name)
+    })
     .collect();
 
   let missing_package_ident_iter = non_workspace_packages
@@ -216,6 +227,86 @@ fn check_resolve_matches_packages(metadata: &Metadata) -> Result<()> {
   )
 }
 
+/// Verifies that the index of every alternate registry is on disk, as planning reads each index to
+/// produce the download URLs of its crates.
+fn check_registry_indexes_are_local(metadata: &Metadata) -> Result<()> {
+  let mut operations: Vec<String> = metadata
+    .packages
+    .iter()
+    .filter(|pkg| !metadata.workspace_members.contains(&pkg.id))
+    .filter_map(|pkg| {
+      let source = pkg
+        .source
+        .as_ref()
+        .filter(|source| !registry::is_crates_io(source))?;
+      let index = match RegistryIndex::from_source(source)? {
+        Ok(index) if index.is_local() => return None,
+        Ok(index) => index.to_string(),
+        Err(_) => source.repr.clone(),
+      };
+
+      Some(format!(
+        "Fetching the crate index {} to produce the download URL of `{}`",
+        index,
+        package_ident(&pkg.name, &pkg.version.to_string())
+      ))
+    })
+    .collect();
+
+  if operations.is_empty() {
+    return Ok(());
+  }
+
+  operations.sort();
+  Err(RazeError::Offline { operations }.into())
+}
+
+/// Verifies that the licenses of all non-workspace packages satisfy the given policy
+fn check_license_policy(metadata: &Metadata, policy: &LicensePolicy) -> Result<()> {
+  if policy.is_empty() {
+    return Ok(());
+  }
+
+  let mut violations: Vec<String> = metadata
+    .packages
+    .iter()
+    .filter(|pkg| !metadata.workspace_members.contains(&pkg.id))
+    .filter(|pkg| !policy.is_exception(&pkg.name, &pkg.version.to_string()))
+    .filter_map(|pkg| {
+      let ident = package_ident(&pkg.name, &pkg.version.to_string());
+      let license = pkg.license.as_deref().unwrap_or_default();
+      // A license file can't be evaluated, so it's up to the policy whether one is acceptable
+      if let (true, Some(license_file)) = (license.is_empty(), &pkg.license_file) {
+        if policy.allow_license_files {
+          eprintln!(
+            "WARNING: {} only declares the license file \"{}\", which must be reviewed by hand",
+            ident, license_file
+          );
+          return None;
+        }
+        return Some(format!(
+          "{} with license file \"{}\": Only a license file is declared. Set \
+           `allow_license_files` to permit it, or add the crate to `exceptions`",
+          ident, license_file
+        ));
+      }
+
+      find_license_policy_violation(license, policy)
+        .map(|reason| format!("{} with license \"{}\": {}", ident, license, reason))
+    })
+    .collect();
+
+  if violations.is_empty() {
+    return Ok(());
+  }
+
+  violations.sort();
+  Err(anyhow!(
+    "The licenses of the following crates violate the `raze.license_policy` setting:\n  {}",
+    violations.join("\n  ")
+  ))
+}
+
 fn warn_unused_settings(
   all_crate_settings: &HashMap<String, CrateSettingsPerVersion>,
   all_packages: &[Package],
@@ -261,6 +352,7 @@ mod tests {
   use super::*;
   use crate::{
     metadata::tests::dummy_raze_metadata,
+    planning::license::BazelLicenseType,
     settings::tests::dummy_raze_settings,
     testing::{template_metadata, templates},
   };
@@ -335,4 +427,84 @@ mod tests {
     // Ensure no checks fail
     check_lockfile_for_missing_checksums(&raze_metadata.metadata, &checksums).unwrap();
   }
+
+  #[test]
+  fn test_offline_mode_requires_local_registry_indexes() {
+    let mut metadata = template_metadata(templates::DUMMY_MODIFIED_METADATA);
+    check_registry_indexes_are_local(&metadata).unwrap();
+
+    let workspace_members = metadata.workspace_members.clone();
+    let package = metadata
+      .packages
+      .iter_mut()
+      .find(|pkg| !workspace_members.contains(&pkg.id))
+      .unwrap();
+    package.source = Some(cargo_metadata::Source {
+      repr: "registry+https://raze.example.com/index".to_owned(),
+    });
+    let ident = package_ident(&package.name, &package.version.to_string());
+
+    let err = check_registry_indexes_are_local(&metadata).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "Raze is running in offline mode, but the following need network access:\n  Fetching the \
+         crate index https://raze.example.com/index to produce the download URL of `{}`",
+        ident
+      )
+    );
+  }
+
+  #[test]
+  fn test_license_policy_lists_offending_crates() {
+    let mut metadata = template_metadata(templates::DUMMY_MODIFIED_METADATA);
+    for package in metadata.packages.iter_mut() {
+      package.license = Some("GPL-3.0-only".to_owned());
+    }
+
+    let mut policy = LicensePolicy {
+      max_rating: Some(BazelLicenseType::Notice),
+      ..LicensePolicy::default()
+    };
+    let err = check_license_policy(&metadata, &policy).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "The licenses of the following crates violate the `raze.license_policy` setting:\n  \
+       test_dep-0.0.1 with license \"GPL-3.0-only\": Not permitted to use GPL-3.0-only"
+    );
+
+    // Exempt crates are not checked
+    policy.exceptions.push("test_dep".to_owned());
+    check_license_policy(&metadata, &policy).unwrap();
+  }
+
+  #[test]
+  fn test_license_policy_with_license_files() {
+    let mut metadata = template_metadata(templates::DUMMY_MODIFIED_METADATA);
+    for package in metadata.packages.iter_mut() {
+      package.license = None;
+      package.license_file = Some("LICENSE.txt".into());
+    }
+
+    let mut policy = LicensePolicy {
+      max_rating: Some(BazelLicenseType::Notice),
+      ..LicensePolicy::default()
+    };
+    let err = check_license_policy(&metadata, &policy).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "The licenses of the following crates violate the `raze.license_policy` setting:\n  \
+       test_dep-0.0.1 with license file \"LICENSE.txt\": Only a license file is declared. Set \
+       `allow_license_files` to permit it, or add the crate to `exceptions`"
+    );
+
+    policy.allow_license_files = true;
+    check_license_policy(&metadata, &policy).unwrap();
+
+    // Crates declaring neither are still violations
+    for package in metadata.packages.iter_mut() {
+      package.license_file = None;
+    }
+    assert!(check_license_policy(&metadata, &policy).is_err());
+  }
 }
@@ -14,7 +14,10 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
-use crate::{features::Features, settings::CrateSettings};
+use crate::{
+  features::Features,
+  settings::{CrateSettings, RegistryAuth, TargetedRustcSettings},
+};
 use camino::Utf8PathBuf;
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -57,6 +60,10 @@ pub struct Metadep {
 pub struct LicenseData {
   pub name: String,
   pub rating: String,
+  // The crate's SPDX license expression, or an empty string if it declares none
+  pub expression: String,
+  // The SPDX ids of the licenses in the expression, sorted and without duplicates
+  pub spdx_ids: Vec<String>,
 }
 
 impl Default for LicenseData {
@@ -64,6 +71,8 @@ impl Default for LicenseData {
     LicenseData {
       name: "no license".into(),
       rating: "restricted".into(),
+      expression: "".into(),
+      spdx_ids: Vec::new(),
     }
   }
 }
@@ -82,6 +91,8 @@ pub struct GitRepo {
 pub struct SourceDetails {
   pub git_data: Option<GitRepo>,
   pub download_url: Option<Url>,
+  /// The credentials needed to fetch `download_url`, if its registry requires authentication
+  pub registry_auth: Option<RegistryAuth>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -155,6 +166,8 @@ pub struct CrateContext {
   pub canonical_additional_build_file: Option<Utf8PathBuf>,
   pub default_deps: CrateDependencyContext,
   pub targeted_deps: Vec<CrateTargetedDepContext>,
+  // The crate's `targeted_rustc_settings`, resolved and merged per platform triple
+  pub platform_rustc_settings: BTreeMap<String, TargetedRustcSettings>,
   pub license: LicenseData,
   pub features: Features,
   pub workspace_path_to_crate: String,
@@ -166,9 +179,19 @@ pub struct CrateContext {
   pub targets: Vec<BuildableTarget>,
   pub build_script_target: Option<BuildableTarget>,
   pub links: Option<String>,
+  // The `homepage` and `repository` URLs declared in the crate's Cargo.toml
+  pub homepage: Option<String>,
+  pub repository: Option<String>,
   pub source_details: SourceDetails,
   pub sha256: Option<String>,
 
+  // The license, copying and notice files of the crate, relative to `package_root`
+  pub license_files: Vec<Utf8PathBuf>,
+  // The directory containing the crate's sources. This is specific to the machine cargo-raze was
+  // run on, so it is omitted when serialized.
+  #[serde(skip)]
+  pub package_root: Utf8PathBuf,
+
   // TODO(acmcarther): This is used internally by renderer to know where to put the build file. It
   // probably should live somewhere else. Renderer params (separate from context) should live
   // somewhere more explicit.
@@ -184,6 +207,18 @@ pub struct CrateContext {
   pub is_proc_macro: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct WorkspaceMemberPackage {
+  pub name: String,
+  pub version: Version,
+
+  // The relative path from the Cargo workspace root to the package.
+  pub path: Utf8PathBuf,
+
+  // The paths of the other workspace members this package has normal or build dependencies on.
+  pub workspace_member_dependencies: Vec<Utf8PathBuf>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct WorkspaceContext {
   // The bazel path prefix to the vendor directory
@@ -202,4 +237,10 @@ pub struct WorkspaceContext {
 
   // A list of relative paths from a Cargo workspace root to a Cargo package.
   pub workspace_members: Vec<Utf8PathBuf>,
+
+  // The name, version and workspace dependencies of each package in `workspace_members`.
+  pub workspace_member_packages: Vec<WorkspaceMemberPackage>,
+
+  // A mapping of custom target triples to the labels of the Bazel platforms which select them.
+  pub custom_platforms: BTreeMap<String, String>,
 }
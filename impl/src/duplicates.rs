@@ -0,0 +1,342 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use anyhow::Result;
+use cargo_metadata::PackageId;
+use itertools::Itertools;
+use semver::Version;
+use serde::Serialize;
+
+use crate::{error::RazeError, metadata::RazeMetadata, planning::crate_catalog::CrateCatalog};
+
+/// The change to a crate's version needed to move from one version to another.
+///
+/// Versions are compared using Cargo's compatibility rules, so moving from `0.1.x` to `0.2.x`
+/// is a major bump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SemverBump {
+  Major,
+  Minor,
+  Patch,
+}
+
+impl SemverBump {
+  pub fn between(from: &Version, to: &Version) -> SemverBump {
+    let is_compatible = match (to.major, to.minor) {
+      (0, 0) => from.major == 0 && from.minor == 0 && from.patch == to.patch,
+      (0, minor) => from.major == 0 && from.minor == minor,
+      (major, _) => from.major == major,
+    };
+
+    if !is_compatible {
+      SemverBump::Major
+    } else if from.major != to.major || from.minor != to.minor {
+      SemverBump::Minor
+    } else {
+      SemverBump::Patch
+    }
+  }
+}
+
+/// A crate which directly depends on one version of a duplicated crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionDependent {
+  pub name: String,
+  pub version: Version,
+  pub is_workspace_member: bool,
+  /// The version requirements this crate declares on the duplicated crate
+  pub requirements: Vec<String>,
+  /// Whether the requirements would have to be changed to accept the newest version
+  pub needs_update: bool,
+}
+
+/// One of the versions of a duplicated crate in the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateVersion {
+  pub version: Version,
+  /// The bump needed to move to the newest version, or `None` for the newest version itself
+  pub bump: Option<SemverBump>,
+  pub dependents: Vec<VersionDependent>,
+  /// The workspace members which directly or transitively depend on this version
+  pub workspace_members: Vec<String>,
+}
+
+/// A crate present in more than one version in the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateCrate {
+  pub name: String,
+  /// The newest version, which all other versions would need to be bumped to to unify them
+  pub newest_version: Version,
+  /// Each version of the crate, from oldest to newest
+  pub versions: Vec<DuplicateVersion>,
+}
+
+/// Finds every crate in the resolve graph which is present in more than one version.
+pub fn find_duplicate_versions(metadata: &RazeMetadata) -> Result<Vec<DuplicateCrate>> {
+  let catalog = CrateCatalog::new(&metadata.metadata)?;
+  let resolve = metadata
+    .metadata
+    .resolve
+    .as_ref()
+    .ok_or_else(|| RazeError::Generic("Missing resolve graph".into()))?;
+
+  let mut dependents: HashMap<&PackageId, BTreeSet<&PackageId>> = HashMap::new();
+  for node in &resolve.nodes {
+    for dep in &node.deps {
+      dependents.entry(&dep.pkg).or_default().insert(&node.id);
+    }
+  }
+
+  let mut versions_by_name: BTreeMap<&str, Vec<&PackageId>> = BTreeMap::new();
+  for node in &resolve.nodes {
+    if let Some(entry) = catalog.entry_for_package_id(&node.id) {
+      versions_by_name
+        .entry(&entry.package().name)
+        .or_default()
+        .push(&node.id);
+    }
+  }
+
+  let duplicates = versions_by_name
+    .into_iter()
+    .filter(|(_, ids)| ids.len() > 1)
+    .map(|(name, ids)| {
+      // UNWRAP: Ids are taken from catalog entries
+      let packages = ids
+        .iter()
+        .map(|id| catalog.entry_for_package_id(id).unwrap().package())
+        .sorted_by_key(|pkg| &pkg.version)
+        .collect_vec();
+      // UNWRAP: Duplicated crates have at least two versions
+      let newest_version = packages.last().unwrap().version.clone();
+
+      let versions = packages
+        .iter()
+        .map(|pkg| {
+          let is_newest = pkg.version == newest_version;
+
+          let version_dependents = dependents
+            .get(&pkg.id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| catalog.entry_for_package_id(id))
+            .map(|entry| {
+              let dependencies = entry
+                .package()
+                .dependencies
+                .iter()
+                .filter(|dep| dep.name == name && dep.req.matches(&pkg.version))
+                .collect_vec();
+
+              VersionDependent {
+                name: entry.package().name.clone(),
+                version: entry.package().version.clone(),
+                is_workspace_member: entry.is_workspace_crate(),
+                requirements: dependencies
+                  .iter()
+                  .map(|dep| dep.req.to_string())
+                  .unique()
+                  .collect(),
+                needs_update: !is_newest
+                  && dependencies
+                    .iter()
+                    .any(|dep| !dep.req.matches(&newest_version)),
+              }
+            })
+            .sorted_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)))
+            .collect_vec();
+
+          DuplicateVersion {
+            version: pkg.version.clone(),
+            bump: (!is_newest).then(|| SemverBump::between(&pkg.version, &newest_version)),
+            dependents: version_dependents,
+            workspace_members: find_workspace_members(&pkg.id, &dependents, &catalog),
+          }
+        })
+        .collect_vec();
+
+      DuplicateCrate {
+        name: name.to_owned(),
+        newest_version,
+        versions,
+      }
+    })
+    .collect();
+
+  Ok(duplicates)
+}
+
+/// Finds the workspace members which directly or transitively depend on `id`.
+fn find_workspace_members(
+  id: &PackageId,
+  dependents: &HashMap<&PackageId, BTreeSet<&PackageId>>,
+  catalog: &CrateCatalog,
+) -> Vec<String> {
+  let mut members = BTreeSet::new();
+  let mut visited = BTreeSet::new();
+  let mut queue = VecDeque::from(vec![id]);
+  while let Some(id) = queue.pop_front() {
+    for dependent in dependents.get(id).into_iter().flatten() {
+      if !visited.insert(*dependent) {
+        continue;
+      }
+
+      match catalog.entry_for_package_id(dependent) {
+        // Paths through other workspace members are attributed to the first member reached
+        Some(entry) if entry.is_workspace_crate() => {
+          members.insert(entry.package().name.clone());
+        }
+        _ => queue.push_back(dependent),
+      }
+    }
+  }
+
+  members.into_iter().collect()
+}
+
+/// Renders a duplicate version report for printing to the terminal.
+pub fn render_duplicates_report(duplicates: &[DuplicateCrate]) -> String {
+  if duplicates.is_empty() {
+    return "No crates are present in more than one version\n".to_owned();
+  }
+
+  let mut output = String::new();
+  for duplicate in duplicates {
+    output.push_str(&format!(
+      "{} is present in {} versions\n",
+      duplicate.name,
+      duplicate.versions.len()
+    ));
+
+    for version in &duplicate.versions {
+      match version.bump {
+        Some(bump) => output.push_str(&format!(
+          "  v{} ({:?} bump to v{})\n",
+          version.version, bump, duplicate.newest_version
+        )),
+        None => output.push_str(&format!("  v{}\n", version.version)),
+      }
+
+      for dependent in &version.dependents {
+        output.push_str(&format!(
+          "    {} {} v{} requires {}{}\n",
+          if dependent.is_workspace_member {
+            "member"
+          } else {
+            "crate "
+          },
+          dependent.name,
+          dependent.version,
+          dependent.requirements.join(", "),
+          if dependent.needs_update {
+            format!(", which does not accept v{}", duplicate.newest_version)
+          } else {
+            String::new()
+          }
+        ));
+      }
+
+      if !version.workspace_members.is_empty() {
+        output.push_str(&format!(
+          "    used by workspace members: {}\n",
+          version.workspace_members.join(", ")
+        ));
+      }
+    }
+    output.push('\n');
+  }
+
+  output.push_str(&match duplicates.len() {
+    1 => "1 crate is present in more than one version\n".to_owned(),
+    count => format!("{} crates are present in more than one version\n", count),
+  });
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::testing::{template_raze_metadata, templates};
+
+  #[test]
+  fn test_semver_bump_between_versions() {
+    let bump = |from: &str, to: &str| {
+      SemverBump::between(&Version::parse(from).unwrap(), &Version::parse(to).unwrap())
+    };
+
+    assert_eq!(bump("1.0.109", "2.0.38"), SemverBump::Major);
+    assert_eq!(bump("0.3.9", "0.4.11"), SemverBump::Major);
+    assert_eq!(bump("0.0.1", "0.0.2"), SemverBump::Major);
+    assert_eq!(bump("1.0.0", "1.2.0"), SemverBump::Minor);
+    assert_eq!(bump("0.4.0", "0.4.11"), SemverBump::Patch);
+  }
+
+  #[test]
+  fn test_find_duplicate_versions() {
+    let duplicates =
+      find_duplicate_versions(&template_raze_metadata(templates::SEMVER_MATCHING)).unwrap();
+    assert_eq!(
+      duplicates.iter().map(|dup| dup.name.as_str()).collect_vec(),
+      vec!["log", "memchr", "winapi"]
+    );
+
+    let memchr = &duplicates[1];
+    assert_eq!(memchr.newest_version, Version::new(2, 3, 4));
+
+    let old_memchr = &memchr.versions[0];
+    assert_eq!(old_memchr.version, Version::new(1, 0, 2));
+    assert_eq!(old_memchr.bump, Some(SemverBump::Major));
+    assert_eq!(
+      old_memchr.dependents,
+      vec![VersionDependent {
+        name: "nom".to_owned(),
+        version: Version::new(3, 2, 1),
+        is_workspace_member: false,
+        requirements: vec!["^1.0.1".to_owned()],
+        needs_update: true,
+      }]
+    );
+    assert_eq!(old_memchr.workspace_members, vec!["semver_toml"]);
+
+    let new_memchr = &memchr.versions[1];
+    assert_eq!(new_memchr.bump, None);
+    assert_eq!(
+      new_memchr
+        .dependents
+        .iter()
+        .map(|dep| (dep.name.as_str(), dep.needs_update))
+        .collect_vec(),
+      vec![("aho-corasick", false), ("regex", false)]
+    );
+
+    let report = render_duplicates_report(&duplicates);
+    assert!(report.contains("memchr is present in 2 versions\n  v1.0.2 (Major bump to v2.3.4)\n"));
+    assert!(report.ends_with("3 crates are present in more than one version\n"));
+  }
+
+  #[test]
+  fn test_no_duplicate_versions() {
+    let duplicates =
+      find_duplicate_versions(&template_raze_metadata(templates::BASIC_METADATA)).unwrap();
+    assert!(duplicates.is_empty());
+    assert_eq!(
+      render_duplicates_report(&duplicates),
+      "No crates are present in more than one version\n"
+    );
+  }
+}
@@ -33,6 +33,10 @@ pub enum RazeError {
     field_path_opt: Option<String>,
     message: String,
   },
+  /// Operations which need network access but were attempted in offline mode
+  Offline {
+    operations: Vec<String>,
+  },
 }
 
 impl std::error::Error for RazeError {}
@@ -82,6 +86,11 @@ impl fmt::Display for RazeError {
         dependency_name_opt: None,
         message,
       } => write!(f, "Raze failed to render with cause: \"{}\"", message),
+      Self::Offline { operations } => write!(
+        f,
+        "Raze is running in offline mode, but the following need network access:\n  {}",
+        operations.join("\n  ")
+      ),
     }
   }
 }
@@ -0,0 +1,729 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use cargo_metadata::{DepKindInfo, DependencyKind, Package, PackageId};
+use itertools::Itertools;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use crate::{
+  context::CrateContext, error::RazeError, metadata::RazeMetadata, planning::PlannedBuild,
+};
+
+/// One way in which a crate depends on another, as declared in the dependent's Cargo.toml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EdgeKind {
+  /// The kind of the dependency: `normal`, `build` or `dev`
+  pub kind: String,
+  /// The platform `cfg` expression or target triple the dependency is limited to, if any
+  pub target: Option<String>,
+  /// The features the dependent enables on the dependency
+  pub features: Vec<String>,
+}
+
+/// A dependency on the crate named by `name` and `version` from the crate preceding it in a path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyEdge {
+  pub name: String,
+  pub version: Version,
+  pub kinds: Vec<EdgeKind>,
+}
+
+/// The most paths listed for a single version of a crate when every path is requested
+pub const MAX_LISTED_PATHS: usize = 1000;
+
+/// A chain of dependencies leading from a workspace member to an explained crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyPath {
+  pub member_name: String,
+  pub member_version: Version,
+  /// The dependencies taken from the workspace member, ending with the explained crate
+  pub edges: Vec<DependencyEdge>,
+  /// The number of distinct paths this path stands for: all paths from the workspace member when
+  /// only the shortest one is listed, or 1 when every path is
+  pub path_count: u64,
+}
+
+/// How a single version of a crate becomes part of the build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrateExplanation {
+  pub name: String,
+  pub version: Version,
+  /// The number of distinct paths from all workspace members to the crate, saturating at
+  /// `u64::MAX`
+  pub path_count: u64,
+  /// The shortest path from each workspace member, or every path up to `MAX_LISTED_PATHS`
+  pub paths: Vec<DependencyPath>,
+}
+
+/// Finds how each version of the crate named `name` which matches `version_req` is depended on by
+/// workspace members, and counts all paths between them.
+///
+/// Only the shortest path from each workspace member is listed, unless `all_paths` is set. As the
+/// number of paths can grow exponentially with the depth of the graph, at most `MAX_LISTED_PATHS`
+/// are then listed for each version.
+///
+/// Edges are taken from the resolve graph of `metadata`. Dependencies of crates that were
+/// planned are only followed if they are part of the dependency sets of their `CrateContext`,
+/// so dependencies which were skipped or targeted away are not reported.
+pub fn explain_crate(
+  metadata: &RazeMetadata,
+  planned_build: &PlannedBuild,
+  name: &str,
+  version_req: Option<&VersionReq>,
+  all_paths: bool,
+) -> Result<Vec<CrateExplanation>> {
+  let resolve = metadata
+    .metadata
+    .resolve
+    .as_ref()
+    .ok_or_else(|| RazeError::Generic("Missing resolve graph".into()))?;
+
+  let packages: HashMap<&PackageId, &Package> = metadata
+    .metadata
+    .packages
+    .iter()
+    .map(|pkg| (&pkg.id, pkg))
+    .collect();
+
+  let explained_ids = resolve
+    .nodes
+    .iter()
+    .filter_map(|node| packages.get(&node.id))
+    .filter(|pkg| pkg.name == name)
+    .filter(|pkg| match version_req {
+      Some(req) => req.matches(&pkg.version),
+      None => true,
+    })
+    .sorted_by_key(|pkg| &pkg.version)
+    .map(|pkg| &pkg.id)
+    .collect_vec();
+
+  if explained_ids.is_empty() {
+    return Err(
+      RazeError::Generic(match version_req {
+        Some(req) => format!(
+          "No version of `{}` matching `{}` is in the build",
+          name, req
+        ),
+        None => format!("`{}` is not in the build", name),
+      })
+      .into(),
+    );
+  }
+
+  // Reverse the resolve graph, so paths can be walked back from the explained crate
+  let mut dependents: HashMap<&PackageId, Vec<(&PackageId, DependencyEdge)>> = HashMap::new();
+  for node in &resolve.nodes {
+    let dependent = match packages.get(&node.id) {
+      Some(pkg) => pkg,
+      None => continue,
+    };
+    let crate_context = planned_build
+      .crate_contexts
+      .iter()
+      .find(|ctx| ctx.pkg_name == dependent.name && ctx.pkg_version == dependent.version);
+
+    for dep in &node.deps {
+      let dep_package = match packages.get(&dep.pkg) {
+        Some(pkg) => pkg,
+        None => continue,
+      };
+
+      let kinds = dep
+        .dep_kinds
+        .iter()
+        .filter(|dep_kind| is_planned(crate_context, dep_package, dep_kind))
+        .map(|dep_kind| produce_edge_kind(dependent, dep_package, dep_kind))
+        .collect_vec();
+      if kinds.is_empty() {
+        continue;
+      }
+
+      dependents.entry(&dep.pkg).or_default().push((
+        &node.id,
+        DependencyEdge {
+          name: dep_package.name.clone(),
+          version: dep_package.version.clone(),
+          kinds,
+        },
+      ));
+    }
+  }
+
+  let workspace_members: HashSet<&PackageId> = metadata.metadata.workspace_members.iter().collect();
+
+  let mut member_paths = HashMap::new();
+  Ok(
+    explained_ids
+      .into_iter()
+      .map(|id| {
+        let shortest_paths = walk_dependents(
+          id,
+          &dependents,
+          &workspace_members,
+          &mut HashSet::new(),
+          &mut member_paths,
+        );
+        let path_count = shortest_paths
+          .values()
+          .fold(0, |count: u64, path| count.saturating_add(path.count));
+
+        let listed_paths = if all_paths {
+          let mut listed_paths = Vec::new();
+          list_dependent_paths(
+            id,
+            &dependents,
+            &workspace_members,
+            &member_paths,
+            &mut HashSet::new(),
+            &mut Vec::new(),
+            &mut listed_paths,
+          );
+          listed_paths
+        } else {
+          shortest_paths.into_iter().collect_vec()
+        };
+
+        let mut paths = listed_paths
+          .into_iter()
+          .map(|(member_id, member_path)| {
+            // UNWRAP: Dependents are taken from `packages`
+            let member = packages[member_id];
+            DependencyPath {
+              member_name: member.name.clone(),
+              member_version: member.version.clone(),
+              edges: member_path
+                .edges
+                .iter()
+                .map(|edge| (*edge).clone())
+                .collect(),
+              path_count: member_path.count,
+            }
+          })
+          .collect_vec();
+        paths.sort_by(|a, b| {
+          (
+            &a.member_name,
+            &a.member_version,
+            a.edges.len(),
+            path_names(a),
+          )
+            .cmp(&(
+              &b.member_name,
+              &b.member_version,
+              b.edges.len(),
+              path_names(b),
+            ))
+        });
+
+        // UNWRAP: Explained ids are taken from `packages`
+        let pkg = packages[id];
+        CrateExplanation {
+          name: pkg.name.clone(),
+          version: pkg.version.clone(),
+          path_count,
+          paths,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Renders explanations as a tree per path, for printing to the terminal.
+pub fn render_explanations(explanations: &[CrateExplanation]) -> String {
+  let mut output = String::new();
+  for explanation in explanations {
+    if !output.is_empty() {
+      output.push('\n');
+    }
+
+    output.push_str(&match explanation.path_count {
+      0 => format!(
+        "{} v{} is not depended on by any workspace member\n",
+        explanation.name, explanation.version
+      ),
+      1 => format!(
+        "{} v{} is depended on through 1 path:\n",
+        explanation.name, explanation.version
+      ),
+      count => format!(
+        "{} v{} is depended on through {} paths:\n",
+        explanation.name, explanation.version, count
+      ),
+    });
+
+    for path in &explanation.paths {
+      output.push_str(&format!("\n{} v{}", path.member_name, path.member_version));
+      if path.path_count > 1 {
+        output.push_str(&format!(" (shortest of {} paths)", path.path_count));
+      }
+      output.push('\n');
+      for (depth, edge) in path.edges.iter().enumerate() {
+        output.push_str(&format!(
+          "{}└── {} v{} ({})\n",
+          "    ".repeat(depth),
+          edge.name,
+          edge.version,
+          edge.kinds.iter().map(render_edge_kind).join("; ")
+        ));
+      }
+    }
+
+    // Only some paths are listed when there are more than `MAX_LISTED_PATHS`
+    let listed_path_count = explanation
+      .paths
+      .iter()
+      .fold(0, |count: u64, path| count.saturating_add(path.path_count));
+    if listed_path_count < explanation.path_count {
+      output.push_str(&format!(
+        "\n... and {} more paths\n",
+        explanation.path_count - listed_path_count
+      ));
+    }
+  }
+
+  output
+}
+
+fn path_names(path: &DependencyPath) -> Vec<&str> {
+  path.edges.iter().map(|edge| edge.name.as_str()).collect()
+}
+
+fn render_edge_kind(edge_kind: &EdgeKind) -> String {
+  let mut rendered = edge_kind.kind.clone();
+  if let Some(target) = &edge_kind.target {
+    rendered.push_str(&format!(", {}", target));
+  }
+  if !edge_kind.features.is_empty() {
+    rendered.push_str(&format!(", features: {}", edge_kind.features.join(", ")));
+  }
+  rendered
+}
+
+/// The paths from a single workspace member to a crate.
+#[derive(Debug, Clone)]
+struct MemberPath<'a> {
+  /// The shortest path, ending with the crate. Ties are broken by the names along the path.
+  edges: Vec<&'a DependencyEdge>,
+  /// The number of distinct paths, saturating at `u64::MAX`
+  count: u64,
+}
+
+impl<'a> MemberPath<'a> {
+  fn sort_key(&self) -> (usize, Vec<&'a str>) {
+    (
+      self.edges.len(),
+      self.edges.iter().map(|edge| edge.name.as_str()).collect(),
+    )
+  }
+}
+
+/// Walks the dependents of `id` back to workspace members, returning the paths from each member
+/// which reaches `id`. Walks stop at the first workspace member found.
+///
+/// The paths of every crate are memoized in `member_paths`, so each dependency edge is only
+/// followed once no matter how many paths lead through it.
+fn walk_dependents<'a>(
+  id: &'a PackageId,
+  dependents: &'a HashMap<&PackageId, Vec<(&PackageId, DependencyEdge)>>,
+  workspace_members: &HashSet<&PackageId>,
+  walking: &mut HashSet<&'a PackageId>,
+  member_paths: &mut HashMap<&'a PackageId, HashMap<&'a PackageId, MemberPath<'a>>>,
+) -> HashMap<&'a PackageId, MemberPath<'a>> {
+  if let Some(paths) = member_paths.get(id) {
+    return paths.clone();
+  }
+
+  walking.insert(id);
+  let mut paths: HashMap<&'a PackageId, MemberPath<'a>> = HashMap::new();
+  for (dependent, edge) in dependents.get(id).into_iter().flatten() {
+    // Dependency cycles are only possible through dev dependencies, but must not be followed
+    if walking.contains(dependent) {
+      continue;
+    }
+
+    let dependent_paths = if workspace_members.contains(dependent) {
+      HashMap::from([(
+        *dependent,
+        MemberPath {
+          edges: Vec::new(),
+          count: 1,
+        },
+      )])
+    } else {
+      walk_dependents(
+        dependent,
+        dependents,
+        workspace_members,
+        walking,
+        member_paths,
+      )
+    };
+
+    for (member, dependent_path) in dependent_paths {
+      let mut candidate = dependent_path;
+      candidate.edges.push(edge);
+      match paths.get_mut(member) {
+        Some(path) => {
+          path.count = path.count.saturating_add(candidate.count);
+          if candidate.sort_key() < path.sort_key() {
+            path.edges = candidate.edges;
+          }
+        }
+        None => {
+          paths.insert(member, candidate);
+        }
+      }
+    }
+  }
+  walking.remove(id);
+
+  member_paths.insert(id, paths.clone());
+  paths
+}
+
+/// Lists the paths from workspace members to `id`, stopping once `MAX_LISTED_PATHS` are listed.
+///
+/// Like `walk_dependents`, walks stop at the first workspace member found. Dependents which
+/// `walk_dependents` found no workspace member through are not followed, so each walk lists a path.
+fn list_dependent_paths<'a>(
+  id: &'a PackageId,
+  dependents: &'a HashMap<&PackageId, Vec<(&PackageId, DependencyEdge)>>,
+  workspace_members: &HashSet<&PackageId>,
+  member_paths: &HashMap<&'a PackageId, HashMap<&'a PackageId, MemberPath<'a>>>,
+  walking: &mut HashSet<&'a PackageId>,
+  edges: &mut Vec<&'a DependencyEdge>,
+  paths: &mut Vec<(&'a PackageId, MemberPath<'a>)>,
+) {
+  walking.insert(id);
+  for (dependent, edge) in dependents.get(id).into_iter().flatten() {
+    if paths.len() >= MAX_LISTED_PATHS {
+      break;
+    }
+    // Dependency cycles are only possible through dev dependencies, but must not be followed
+    if walking.contains(dependent) {
+      continue;
+    }
+
+    edges.push(edge);
+    if workspace_members.contains(dependent) {
+      paths.push((
+        *dependent,
+        MemberPath {
+          edges: edges.iter().rev().copied().collect(),
+          count: 1,
+        },
+      ));
+    } else if !matches!(member_paths.get(dependent), Some(paths) if paths.is_empty()) {
+      list_dependent_paths(
+        dependent,
+        dependents,
+        workspace_members,
+        member_paths,
+        walking,
+        edges,
+        paths,
+      );
+    }
+    edges.pop();
+  }
+  walking.remove(id);
+}
+
+/// Tests whether a dependency of the crate planned in `crate_context` made it into the build.
+///
+/// Workspace members are not planned, so all of their dependencies are considered part of it.
+fn is_planned(
+  crate_context: Option<&CrateContext>,
+  dep_package: &Package,
+  dep_kind: &DepKindInfo,
+) -> bool {
+  let crate_context = match crate_context {
+    Some(crate_context) => crate_context,
+    None => return true,
+  };
+
+  let version = dep_package.version.clone();
+  if crate_context
+    .default_deps
+    .contains(&dep_package.name, version.clone())
+  {
+    return true;
+  }
+
+  // Platform specific dependencies also present in the default dependencies are omitted from
+  // their targeted dependencies, so those are checked second.
+  let target = match &dep_kind.target {
+    Some(target) => target.to_string(),
+    None => return false,
+  };
+  crate_context
+    .targeted_deps
+    .iter()
+    .filter(|targeted| targeted.target == target)
+    .any(|targeted| targeted.deps.contains(&dep_package.name, version.clone()))
+}
+
+fn produce_edge_kind(
+  dependent: &Package,
+  dep_package: &Package,
+  dep_kind: &DepKindInfo,
+) -> EdgeKind {
+  let target = dep_kind.target.as_ref().map(|target| target.to_string());
+
+  let features = dependent
+    .dependencies
+    .iter()
+    .filter(|dep| dep.name == dep_package.name)
+    .filter(|dep| dep.kind == dep_kind.kind)
+    .filter(|dep| dep.target.as_ref().map(|target| target.to_string()) == target)
+    .find(|dep| dep.req.matches(&dep_package.version))
+    .map(|dep| {
+      let mut features = dep.features.clone();
+      if dep.uses_default_features && dep_package.features.contains_key("default") {
+        features.insert(0, "default".to_owned());
+      }
+      features
+    })
+    .unwrap_or_default();
+
+  EdgeKind {
+    kind: match dep_kind.kind {
+      DependencyKind::Build => "build",
+      DependencyKind::Development => "dev",
+      _ => "normal",
+    }
+    .to_owned(),
+    target,
+    features,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::{
+    planning::{BuildPlanner, BuildPlannerImpl},
+    settings::tests::dummy_raze_settings,
+    testing::templates,
+  };
+
+  fn dummy_explanation(
+    name: &str,
+    version_req: Option<&str>,
+    all_paths: bool,
+  ) -> Result<Vec<CrateExplanation>> {
+    let metadata = crate::planning::tests::dummy_workspace_crate_metadata(
+      templates::SUBPLAN_OMITS_PLATFORM_DEPS_ALREADY_IN_DEFAULT_DEPS,
+    );
+    let planner = BuildPlannerImpl::new(metadata.clone(), dummy_raze_settings());
+    let planned_build = planner.plan_build(None).unwrap();
+
+    let version_req = version_req.map(|req| VersionReq::parse(req).unwrap());
+    explain_crate(
+      &metadata,
+      &planned_build,
+      name,
+      version_req.as_ref(),
+      all_paths,
+    )
+  }
+
+  #[test]
+  fn test_explain_transitive_dependency() {
+    let explanations = dummy_explanation("adler", None, false).unwrap();
+    assert_eq!(explanations.len(), 1);
+    assert_eq!(explanations[0].version, Version::new(1, 0, 2));
+
+    let paths = &explanations[0].paths;
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].member_name, "flate_project");
+    assert_eq!(paths[0].path_count, 1);
+    assert_eq!(
+      path_names(&paths[0]),
+      vec!["flate2", "miniz_oxide", "adler"]
+    );
+
+    // Both the default and the platform specific dependency on `miniz_oxide` are reported
+    let miniz_oxide = &paths[0].edges[1];
+    assert_eq!(miniz_oxide.kinds.len(), 2);
+    assert_eq!(miniz_oxide.kinds[0].target, None);
+    assert_eq!(
+      miniz_oxide.kinds[1].target.as_deref(),
+      Some("cfg(all(target_arch = \"wasm32\", not(target_os = \"emscripten\")))")
+    );
+
+    let flate2 = &paths[0].edges[0];
+    assert_eq!(
+      flate2.kinds,
+      vec![EdgeKind {
+        kind: "normal".to_owned(),
+        target: None,
+        features: vec!["default".to_owned()],
+      }]
+    );
+
+    let rendered = render_explanations(&explanations);
+    assert!(rendered.starts_with("adler v1.0.2 is depended on through 1 path:\n"));
+    assert!(rendered.contains("\n    └── miniz_oxide v0.4.4 (normal; normal, cfg("));
+  }
+
+  #[test]
+  fn test_explanations_serialize_to_json() {
+    let explanations = dummy_explanation("adler", None, false).unwrap();
+    let json = serde_json::to_value(&explanations).unwrap();
+    assert_eq!(json[0]["name"], "adler");
+    assert_eq!(json[0]["version"], "1.0.2");
+    assert_eq!(json[0]["path_count"], 1);
+    assert_eq!(json[0]["paths"][0]["member_name"], "flate_project");
+    assert_eq!(json[0]["paths"][0]["edges"][2]["name"], "adler");
+    assert_eq!(
+      json[0]["paths"][0]["edges"][0]["kinds"][0]["kind"],
+      "normal"
+    );
+  }
+
+  #[test]
+  fn test_explain_build_dependency() {
+    let explanations = dummy_explanation("autocfg", Some("=1.0"), false).unwrap();
+    let edges = &explanations[0].paths[0].edges;
+    assert_eq!(edges.last().unwrap().kinds[0].kind, "build");
+  }
+
+  #[test]
+  fn test_explain_crate_not_in_build() {
+    assert!(dummy_explanation("openssl-sys", None, false).is_err());
+    assert!(dummy_explanation("adler", Some("=2"), false).is_err());
+  }
+
+  #[test]
+  fn test_walk_dependents_of_diamonds() {
+    // A chain of diamonds, where each crate `jN` depends on `jN+1` through both `aN` and `bN`
+    const DIAMONDS: usize = 40;
+    let id = |name: String| PackageId {
+      repr: format!("{} 1.0.0", name),
+    };
+    let member = id("member".to_owned());
+    let joints = (0..=DIAMONDS).map(|n| id(format!("j{}", n))).collect_vec();
+    let sides = (0..DIAMONDS)
+      .map(|n| (id(format!("a{}", n)), id(format!("b{}", n))))
+      .collect_vec();
+    let edge = |pkg: &PackageId| DependencyEdge {
+      name: pkg.repr.split(' ').next().unwrap().to_owned(),
+      version: Version::new(1, 0, 0),
+      kinds: Vec::new(),
+    };
+
+    let mut dependents: HashMap<&PackageId, Vec<(&PackageId, DependencyEdge)>> = HashMap::new();
+    dependents.insert(&joints[0], vec![(&member, edge(&joints[0]))]);
+    for (n, (a, b)) in sides.iter().enumerate() {
+      dependents.insert(a, vec![(&joints[n], edge(a))]);
+      dependents.insert(b, vec![(&joints[n], edge(b))]);
+      dependents.insert(
+        &joints[n + 1],
+        vec![(b, edge(&joints[n + 1])), (a, edge(&joints[n + 1]))],
+      );
+    }
+
+    let workspace_members = HashSet::from([&member]);
+    let mut member_paths = HashMap::new();
+    let paths = walk_dependents(
+      &joints[DIAMONDS],
+      &dependents,
+      &workspace_members,
+      &mut HashSet::new(),
+      &mut member_paths,
+    );
+
+    assert_eq!(paths.len(), 1);
+    let member_path = &paths[&member];
+    assert_eq!(member_path.count, 1 << DIAMONDS);
+    // The shortest paths are all as long, so the one through every `aN` is taken
+    assert_eq!(member_path.edges.len(), 2 * DIAMONDS + 1);
+    assert_eq!(member_path.edges[1].name, "a0");
+    assert_eq!(
+      member_path.edges[2 * DIAMONDS - 1].name,
+      format!("a{}", DIAMONDS - 1)
+    );
+
+    let explanation = CrateExplanation {
+      name: format!("j{}", DIAMONDS),
+      version: Version::new(1, 0, 0),
+      path_count: member_path.count,
+      paths: vec![DependencyPath {
+        member_name: "member".to_owned(),
+        member_version: Version::new(1, 0, 0),
+        edges: member_path
+          .edges
+          .iter()
+          .map(|edge| (*edge).clone())
+          .collect(),
+        path_count: member_path.count,
+      }],
+    };
+    let rendered = render_explanations(&[explanation]);
+    assert!(rendered.starts_with("j40 v1.0.0 is depended on through 1099511627776 paths:\n"));
+    assert!(rendered.contains("\nmember v1.0.0 (shortest of 1099511627776 paths)\n"));
+    assert!(!rendered.contains("more paths"));
+
+    // Listing every path stops at the limit
+    let mut listed_paths = Vec::new();
+    list_dependent_paths(
+      &joints[DIAMONDS],
+      &dependents,
+      &workspace_members,
+      &member_paths,
+      &mut HashSet::new(),
+      &mut Vec::new(),
+      &mut listed_paths,
+    );
+    assert_eq!(listed_paths.len(), MAX_LISTED_PATHS);
+    assert!(listed_paths
+      .iter()
+      .all(|(_, path)| path.edges.len() == 2 * DIAMONDS + 1 && path.count == 1));
+
+    let explanation = CrateExplanation {
+      name: format!("j{}", DIAMONDS),
+      version: Version::new(1, 0, 0),
+      path_count: member_path.count,
+      paths: listed_paths
+        .iter()
+        .map(|(_, path)| DependencyPath {
+          member_name: "member".to_owned(),
+          member_version: Version::new(1, 0, 0),
+          edges: path.edges.iter().map(|edge| (*edge).clone()).collect(),
+          path_count: path.count,
+        })
+        .collect(),
+    };
+    let rendered = render_explanations(&[explanation]);
+    assert!(!rendered.contains("shortest of"));
+    assert!(rendered.ends_with("\n... and 1099511626776 more paths\n"));
+  }
+
+  #[test]
+  fn test_explain_all_paths() {
+    let shortest = dummy_explanation("adler", None, false).unwrap();
+    let all = dummy_explanation("adler", None, true).unwrap();
+    assert_eq!(all[0].path_count, shortest[0].path_count);
+    assert_eq!(all[0].paths.len() as u64, all[0].path_count);
+    assert!(all[0].paths.iter().all(|path| path.path_count == 1));
+    assert!(all[0].paths.contains(&shortest[0].paths[0]));
+  }
+}
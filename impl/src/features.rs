@@ -13,13 +13,16 @@
 // limitations under the License.
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::process::Command;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 
 use crate::error::RazeError;
 use crate::settings::RazeSettings;
-use crate::util::cargo_bin_path;
-use anyhow::{Error, Result};
+use crate::util::{cargo_accepts_unstable_flags, cargo_bin_path};
+use anyhow::{Context, Error, Result};
 use camino::Utf8PathBuf;
 use cargo_metadata::{Package, PackageId, Version};
 use itertools::Itertools;
@@ -27,6 +30,9 @@ use serde::{Deserialize, Serialize};
 
 type UnconsolidatedFeatures = BTreeMap<PackageId, BTreeMap<String, BTreeSet<String>>>;
 
+/// The maximum number of `cargo tree` invocations to run at once.
+const MAX_CARGO_TREE_WORKERS: usize = 8;
+
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Features {
   pub features: Vec<String>,
@@ -58,16 +64,76 @@ pub struct TargetedFeatures {
 // There is no plan for Cargo to expose platform data in `cargo-metadata`:
 // See https://github.com/rust-lang/cargo/pull/9982#issuecomment-965423533
 //
-// The Rust documentation says `cargo-tree` may stabilize its `--unit-graph` output, which
-// would make this function faster by avoiding mulitple invocations of `cargo-tree`.
-// https://rust-lang.github.io/rfcs/2957-cargo-features2.html#cargo-metadata
+// Cargo's structured `--unit-graph` output is preferred when it's available. It is currently
+// unstable and only accepted by nightly toolchains, so the `cargo-tree` output is parsed otherwise.
+// https://doc.rust-lang.org/cargo/reference/unstable.html#unit-graph
 //
 pub fn get_per_platform_features(
   cargo_dir: &Path,
   settings: &RazeSettings,
   packages: &[Package],
 ) -> Result<BTreeMap<PackageId, Features>> {
-  get_per_platform_features_with_command(cargo_dir, settings, packages, run_cargo_tree)
+  // Other toolchains reject `--unit-graph`, so it's not attempted once per platform
+  if cargo_accepts_unstable_flags(&cargo_bin_path()) {
+    get_per_platform_features_with_fallback(
+      cargo_dir,
+      settings,
+      packages,
+      run_cargo_unit_graph,
+      run_cargo_tree,
+    )
+  } else {
+    get_per_platform_features_with_command(cargo_dir, settings, packages, run_cargo_tree)
+  }
+}
+
+/// Resolves per-platform features using `unit_graph_command`, falling back to `tree_command` if the
+/// unit graph cannot be produced or understood.
+pub fn get_per_platform_features_with_fallback(
+  cargo_dir: &Path,
+  settings: &RazeSettings,
+  packages: &[Package],
+  unit_graph_command: fn(&Path, &str) -> Result<String>,
+  tree_command: fn(&Path, &str) -> Result<String>,
+) -> Result<BTreeMap<PackageId, Features>> {
+  match get_per_platform_features_from_unit_graph(cargo_dir, settings, packages, unit_graph_command)
+  {
+    Ok(features) => Ok(features),
+    Err(err) => {
+      eprintln!(
+        "WARNING: Unable to use the cargo unit graph, falling back to cargo-tree: {:#}",
+        err
+      );
+      get_per_platform_features_with_command(cargo_dir, settings, packages, tree_command)
+    }
+  }
+}
+
+/// Resolves per-platform features from the JSON unit graph produced by `command`.
+pub fn get_per_platform_features_from_unit_graph(
+  cargo_dir: &Path,
+  settings: &RazeSettings,
+  packages: &[Package],
+  command: fn(&Path, &str) -> Result<String>,
+) -> Result<BTreeMap<PackageId, Features>> {
+  let triples = settings.enabled_targets();
+
+  // Map of PackageIds using the keys that the unit graph provides
+  let package_map: HashMap<&str, &PackageId> = packages
+    .iter()
+    .map(|package| (package.id.repr.as_str(), &package.id))
+    .collect();
+
+  let mut triple_map = BTreeMap::new();
+  for (triple, output) in run_per_platform(cargo_dir, triples, command)? {
+    let output =
+      output.with_context(|| format!("Failed to produce a unit graph for target `{}`", triple))?;
+    let packages = packages_by_platform_from_unit_graph(&output, &triple, &package_map)
+      .with_context(|| format!("Failed to process the unit graph for target `{}`", triple))?;
+    triple_map.insert(triple, packages);
+  }
+
+  Ok(consolidate_triple_map(triple_map))
 }
 
 pub fn get_per_platform_features_with_command(
@@ -87,32 +153,78 @@ pub fn get_per_platform_features_with_command(
   }
 
   let mut triple_map = BTreeMap::new();
-  for triple in triples {
-    triple_map.insert(
-      triple.clone(),
-      // TODO: This part is slow, since it runs cargo-tree per-platform.
-      packages_by_platform(
-        clean_cargo_tree_output(&command(cargo_dir, triple.as_str()).map_err(|_err| {
-          Error::new(RazeError::Generic(
-            "Failed to process cargo-tree output.".into(),
-          ))
-        })?),
-        &package_map,
-      )
-      .map_err(|_err| {
-        Error::new(RazeError::Generic(
-          "Failed to segment packages by platform.".into(),
-        ))
-      })?,
-    );
+  for (triple, output) in run_per_platform(cargo_dir, triples, command)? {
+    let output =
+      output.with_context(|| format!("Failed to run cargo-tree for target `{}`", triple))?;
+    let packages =
+      packages_by_platform(clean_cargo_tree_output(&output), &package_map).map_err(|_err| {
+        Error::new(RazeError::Generic(format!(
+          "Failed to segment packages by platform for `{}`.",
+          triple
+        )))
+      })?;
+    triple_map.insert(triple, packages);
   }
 
-  Ok(
-    transpose_keys(triple_map)
-      .into_iter()
-      .map(consolidate_features)
-      .collect(),
-  )
+  Ok(consolidate_triple_map(triple_map))
+}
+
+fn consolidate_triple_map(
+  triple_map: BTreeMap<String, BTreeMap<PackageId, BTreeSet<String>>>,
+) -> BTreeMap<PackageId, Features> {
+  transpose_keys(triple_map)
+    .into_iter()
+    .map(consolidate_features)
+    .collect()
+}
+
+/// Runs `command` for each triple on a bounded pool of worker threads.
+///
+/// The results are keyed by triple, so they are ordered the same regardless of which invocation
+/// finishes first.
+fn run_per_platform(
+  cargo_dir: &Path,
+  triples: BTreeSet<String>,
+  command: fn(&Path, &str) -> Result<String>,
+) -> Result<BTreeMap<String, Result<String>>> {
+  let worker_count = thread::available_parallelism()
+    .map_or(1, NonZeroUsize::get)
+    .min(MAX_CARGO_TREE_WORKERS)
+    .min(triples.len())
+    .max(1);
+
+  let queue = Arc::new(Mutex::new(triples.into_iter()));
+  let (sender, receiver) = mpsc::channel();
+  let workers: Vec<thread::JoinHandle<()>> = (0..worker_count)
+    .map(|_| {
+      let queue = Arc::clone(&queue);
+      let sender = sender.clone();
+      let cargo_dir = cargo_dir.to_path_buf();
+      thread::spawn(move || {
+        // The lock is released before running the command so other workers may proceed
+        let next_triple = || queue.lock().ok().and_then(|mut triples| triples.next());
+        while let Some(triple) = next_triple() {
+          let output = command(&cargo_dir, &triple);
+          if sender.send((triple, output)).is_err() {
+            break;
+          }
+        }
+      })
+    })
+    .collect();
+  // Only the workers hold senders now, so the receiver finishes once they have all exited
+  drop(sender);
+
+  let outputs = receiver.into_iter().collect();
+  for worker in workers {
+    worker.join().map_err(|_err| {
+      Error::new(RazeError::Internal(
+        "A cargo-tree worker thread panicked.".into(),
+      ))
+    })?;
+  }
+
+  Ok(outputs)
 }
 
 fn clean_cargo_tree_output(cargo_tree_output: &str) -> Vec<String> {
@@ -146,14 +258,13 @@ fn run_cargo_tree(cargo_dir: &Path, triple: &str) -> Result<String> {
     .output()
     .map_err(|_err| Error::new(RazeError::Generic("Failed to run cargo-tree.".into())))?;
   if !tree_output.status.success() {
-    eprintln!(
-      "Running `{:?} {}` in {:?} failed, output follows:",
+    return Err(Error::new(RazeError::Generic(format!(
+      "Running `{} {}` in {:?} failed, output follows:\n{}",
       cargo_bin,
       args.iter().join(" "),
-      cargo_dir
-    );
-    eprintln!("{:?}", std::str::from_utf8(&tree_output.stderr));
-    panic!("cargo-tree ran and returned failure, see stderr for details");
+      cargo_dir,
+      String::from_utf8_lossy(&tree_output.stderr)
+    ))));
   }
 
   String::from_utf8(tree_output.stdout).map_err(|_err| {
@@ -163,6 +274,106 @@ fn run_cargo_tree(cargo_dir: &Path, triple: &str) -> Result<String> {
   })
 }
 
+// Runs `cargo build --unit-graph`, which describes every unit Cargo would compile for the target,
+// including the features enabled on each, without building anything.
+fn run_cargo_unit_graph(cargo_dir: &Path, triple: &str) -> Result<String> {
+  let cargo_bin: Utf8PathBuf = cargo_bin_path();
+  let mut cargo_build = Command::new(&cargo_bin);
+  cargo_build.current_dir(cargo_dir);
+  let args = [
+    "build".to_string(),
+    "--unit-graph".to_string(),
+    "-Z".to_string(),
+    "unstable-options".to_string(),
+    "--frozen".to_string(),
+    "--workspace".to_string(),
+    // Include tests and examples so dev-dependencies are resolved, as they are by `cargo tree`
+    "--all-targets".to_string(),
+    format!("--target={}", triple),
+  ];
+  cargo_build.args(args.iter());
+
+  let build_output = cargo_build
+    .output()
+    .map_err(|_err| Error::new(RazeError::Generic("Failed to run cargo-build.".into())))?;
+  if !build_output.status.success() {
+    return Err(Error::new(RazeError::Generic(format!(
+      "Running `{} {}` in {:?} failed, output follows:\n{}",
+      cargo_bin,
+      args.iter().join(" "),
+      cargo_dir,
+      String::from_utf8_lossy(&build_output.stderr)
+    ))));
+  }
+
+  String::from_utf8(build_output.stdout).map_err(|_err| {
+    Error::new(RazeError::Generic(
+      "Failed to convert cargo-build output to UTF-8.".into(),
+    ))
+  })
+}
+
+/// The version of Cargo's unit graph format which can be parsed
+const UNIT_GRAPH_VERSION: u32 = 1;
+
+/// The subset of Cargo's `--unit-graph` output needed to resolve features
+#[derive(Debug, Deserialize)]
+struct UnitGraph {
+  version: u32,
+  units: Vec<Unit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Unit {
+  pkg_id: String,
+  /// The target triple the unit is compiled for, or `None` for units compiled for the host
+  platform: Option<String>,
+  features: Vec<String>,
+}
+
+fn packages_by_platform_from_unit_graph(
+  unit_graph: &str,
+  triple: &str,
+  packages: &HashMap<&str, &PackageId>,
+) -> Result<BTreeMap<PackageId, BTreeSet<String>>> {
+  let unit_graph: UnitGraph = serde_json::from_str(unit_graph)?;
+  if unit_graph.version != UNIT_GRAPH_VERSION {
+    return Err(Error::new(RazeError::Generic(format!(
+      "Unsupported unit graph version {}, expected {}.",
+      unit_graph.version, UNIT_GRAPH_VERSION
+    ))));
+  }
+
+  // A package may have several units, such as a library and its build script, which can each
+  // enable different features. Units built for the host, such as build scripts and proc-macros,
+  // may enable features the target never sees, so they only count for host-only packages.
+  let mut target_packages: BTreeMap<PackageId, BTreeSet<String>> = BTreeMap::new();
+  let mut host_packages: BTreeMap<PackageId, BTreeSet<String>> = BTreeMap::new();
+  for unit in unit_graph.units {
+    let id = packages.get(unit.pkg_id.as_str()).ok_or_else(|| {
+      Error::new(RazeError::Generic(format!(
+        "No PackageId found for unit `{}`.",
+        unit.pkg_id
+      )))
+    })?;
+
+    let package_map = match unit.platform.as_deref() {
+      Some(platform) if platform == triple => &mut target_packages,
+      Some(_) => continue,
+      None => &mut host_packages,
+    };
+    package_map
+      .entry((*id).clone())
+      .or_default()
+      .extend(unit.features);
+  }
+
+  for (id, features) in host_packages {
+    target_packages.entry(id).or_insert(features);
+  }
+  Ok(target_packages)
+}
+
 fn packages_by_platform(
   crates: Vec<String>,
   packages: &HashMap<(String, Version), PackageId>,
@@ -405,6 +616,191 @@ proc-macro2 v1.0.26|default,proc-macro| (*)
     Ok(read_to_string(cargo_tree_content).unwrap())
   }
 
+  fn failing_cargo_tree_command(cargo_dir: &Path, triple: &str) -> Result<String> {
+    if triple == "x86_64-pc-windows-msvc" {
+      return Err(Error::new(RazeError::Generic("cargo-tree exploded".into())));
+    }
+    mock_cargo_tree_command(cargo_dir, triple)
+  }
+
+  #[test]
+  fn test_per_platform_errors_name_the_failing_triple() {
+    let temp_dir = make_basic_workspace();
+    let mut settings = dummy_raze_settings();
+    settings.target = None;
+    settings.targets = Some(
+      vec![
+        "aarch64-apple-darwin".to_owned(),
+        "x86_64-pc-windows-msvc".to_owned(),
+        "x86_64-unknown-linux-gnu".to_owned(),
+      ]
+      .into_iter()
+      .collect(),
+    );
+
+    let metadata = dummy_workspace_crate_metadata(templates::CARGO_TREE);
+    let err = get_per_platform_features_with_command(
+      temp_dir.path(),
+      &settings,
+      &metadata.metadata.packages,
+      failing_cargo_tree_command,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "Failed to run cargo-tree for target `x86_64-pc-windows-msvc`"
+    );
+    assert!(format!("{:#}", err).contains("cargo-tree exploded"));
+  }
+
+  fn mock_unit_graph_command(_cargo_dir: &Path, triple: &str) -> Result<String> {
+    let tokio_features = if triple.contains("windows") {
+      r#"["default", "winapi"]"#
+    } else {
+      r#"["default", "libc", "mio"]"#
+    };
+
+    Ok(format!(
+      r#"{{
+        "version": 1,
+        "units": [
+          {{
+            "pkg_id": "tokio 1.17.0 (registry+https://github.com/rust-lang/crates.io-index)",
+            "target": {{ "kind": ["lib"], "name": "tokio" }},
+            "platform": "{triple}",
+            "features": {tokio_features}
+          }},
+          {{
+            "pkg_id": "mio 0.8.2 (registry+https://github.com/rust-lang/crates.io-index)",
+            "target": {{ "kind": ["lib"], "name": "mio" }},
+            "platform": "{triple}",
+            "features": []
+          }},
+          {{
+            "pkg_id": "mio 0.8.2 (registry+https://github.com/rust-lang/crates.io-index)",
+            "target": {{ "kind": ["custom-build"], "name": "build-script-build" }},
+            "platform": null,
+            "features": ["net", "os-poll"]
+          }},
+          {{
+            "pkg_id": "log 0.4.16 (registry+https://github.com/rust-lang/crates.io-index)",
+            "target": {{ "kind": ["lib"], "name": "log" }},
+            "platform": null,
+            "features": ["std"]
+          }}
+        ],
+        "roots": [0]
+      }}"#,
+      triple = triple,
+      tokio_features = tokio_features,
+    ))
+  }
+
+  fn unavailable_unit_graph_command(_cargo_dir: &Path, _triple: &str) -> Result<String> {
+    Err(Error::new(RazeError::Generic(
+      "the `-Z` flag is only accepted on the nightly channel of Cargo".into(),
+    )))
+  }
+
+  fn settings_with_targets(targets: &[&str]) -> RazeSettings {
+    let mut settings = dummy_raze_settings();
+    settings.genmode = GenMode::Vendored;
+    settings.target = None;
+    settings.targets = Some(targets.iter().map(|target| target.to_string()).collect());
+    settings
+  }
+
+  #[test]
+  fn test_per_platform_features_from_unit_graph() {
+    let temp_dir = make_basic_workspace();
+    let settings = settings_with_targets(&["x86_64-pc-windows-msvc", "x86_64-unknown-linux-gnu"]);
+    let metadata = dummy_workspace_crate_metadata(templates::CARGO_TREE);
+
+    let features = get_per_platform_features_with_fallback(
+      temp_dir.path(),
+      &settings,
+      &metadata.metadata.packages,
+      mock_unit_graph_command,
+      failing_cargo_tree_command,
+    )
+    .unwrap();
+
+    let tokio = features
+      .iter()
+      .find(|(id, _)| id.repr.starts_with("tokio "))
+      .unwrap()
+      .1;
+    assert_eq!(tokio.features, vec!["default"]);
+    assert_eq!(
+      tokio.targeted_features,
+      vec![
+        TargetedFeatures {
+          platforms: vec!["x86_64-pc-windows-msvc".to_owned()],
+          features: vec!["winapi".to_owned()],
+        },
+        TargetedFeatures {
+          platforms: vec!["x86_64-unknown-linux-gnu".to_owned()],
+          features: vec!["libc".to_owned(), "mio".to_owned()],
+        },
+      ]
+    );
+
+    // Features of units built for the host don't leak into those built for the target
+    let mio = features
+      .iter()
+      .find(|(id, _)| id.repr.starts_with("mio "))
+      .unwrap()
+      .1;
+    assert!(mio.features.is_empty());
+    assert!(mio.targeted_features.is_empty());
+
+    // Packages only built for the host use the features of their host units
+    let log = features
+      .iter()
+      .find(|(id, _)| id.repr.starts_with("log "))
+      .unwrap()
+      .1;
+    assert_eq!(log.features, vec!["std"]);
+  }
+
+  #[test]
+  fn test_per_platform_features_fall_back_to_cargo_tree() {
+    let temp_dir = make_basic_workspace();
+    let settings = settings_with_targets(&["x86_64-pc-windows-msvc", "x86_64-unknown-linux-gnu"]);
+    let metadata = dummy_workspace_crate_metadata(templates::CARGO_TREE);
+
+    let features = get_per_platform_features_with_fallback(
+      temp_dir.path(),
+      &settings,
+      &metadata.metadata.packages,
+      unavailable_unit_graph_command,
+      mock_cargo_tree_command,
+    )
+    .unwrap();
+    let tree_features = get_per_platform_features_with_command(
+      temp_dir.path(),
+      &settings,
+      &metadata.metadata.packages,
+      mock_cargo_tree_command,
+    )
+    .unwrap();
+
+    assert_eq!(features, tree_features);
+  }
+
+  #[test]
+  fn test_unit_graph_rejects_unknown_versions() {
+    let package_map = HashMap::new();
+    let err = packages_by_platform_from_unit_graph(
+      r#"{"version": 2, "units": []}"#,
+      "x86_64-unknown-linux-gnu",
+      &package_map,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("Unsupported unit graph version 2"));
+  }
+
   #[test]
   fn test_per_platform_plan() {
     let platforms = vec![
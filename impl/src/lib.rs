@@ -14,10 +14,14 @@
 
 pub mod checks;
 pub mod context;
+pub mod duplicates;
 pub mod error;
+pub mod explain;
 pub mod features;
+pub mod lockfile_diff;
 pub mod metadata;
 pub mod planning;
+pub mod registry;
 pub mod rendering;
 pub mod settings;
 pub mod util;
@@ -0,0 +1,320 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cargo_lock::{Lockfile, Package};
+use itertools::{EitherOrBoth, Itertools};
+use semver::Version;
+use serde::Serialize;
+
+/// A package as it is recorded in a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct LockedPackage {
+  pub version: Version,
+  pub source: Option<String>,
+  pub checksum: Option<String>,
+}
+
+impl LockedPackage {
+  fn new(package: &Package) -> Self {
+    Self {
+      version: package.version.clone(),
+      source: package.source.as_ref().map(|source| source.to_string()),
+      checksum: package
+        .checksum
+        .as_ref()
+        .map(|checksum| checksum.to_string()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+  Added,
+  Removed,
+  Upgraded,
+  Downgraded,
+  /// The version is unchanged, but its source or checksum is not
+  Modified,
+}
+
+/// A change to a single package between two lockfiles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockfileChange {
+  pub name: String,
+  pub kind: ChangeKind,
+  /// The package in the previous lockfile, unless it was added
+  pub old: Option<LockedPackage>,
+  /// The package in the new lockfile, unless it was removed
+  pub new: Option<LockedPackage>,
+}
+
+/// Compares two lockfiles, returning the changed packages sorted by name.
+///
+/// Packages are matched by name. When several versions of a package are locked, versions
+/// present in both lockfiles are matched first, and the remaining versions are paired from
+/// newest to oldest.
+pub fn diff_lockfiles(old: &Lockfile, new: &Lockfile) -> Vec<LockfileChange> {
+  let group_by_name = |lockfile: &Lockfile| {
+    let mut packages: BTreeMap<String, BTreeSet<LockedPackage>> = BTreeMap::new();
+    for package in &lockfile.packages {
+      packages
+        .entry(package.name.as_str().to_owned())
+        .or_default()
+        .insert(LockedPackage::new(package));
+    }
+    packages
+  };
+  let old_packages = group_by_name(old);
+  let new_packages = group_by_name(new);
+
+  let names: BTreeSet<&String> = old_packages.keys().chain(new_packages.keys()).collect();
+  let mut changes = Vec::new();
+  for name in names {
+    let empty = BTreeSet::new();
+    let old_versions = old_packages.get(name).unwrap_or(&empty);
+    let new_versions = new_packages.get(name).unwrap_or(&empty);
+
+    // Identical packages are unchanged, and packages sharing a version were modified in place
+    let mut old_remaining = old_versions.difference(new_versions).collect_vec();
+    let mut new_remaining = new_versions.difference(old_versions).collect_vec();
+    old_remaining.retain(|old_pkg| {
+      match new_remaining
+        .iter()
+        .position(|new_pkg| new_pkg.version == old_pkg.version)
+      {
+        Some(idx) => {
+          changes.push(LockfileChange {
+            name: name.clone(),
+            kind: ChangeKind::Modified,
+            old: Some((*old_pkg).clone()),
+            new: Some(new_remaining.remove(idx).clone()),
+          });
+          false
+        }
+        None => true,
+      }
+    });
+
+    for pair in old_remaining
+      .into_iter()
+      .rev()
+      .zip_longest(new_remaining.into_iter().rev())
+    {
+      let (kind, old_pkg, new_pkg) = match pair {
+        EitherOrBoth::Both(old_pkg, new_pkg) if new_pkg.version > old_pkg.version => {
+          (ChangeKind::Upgraded, Some(old_pkg), Some(new_pkg))
+        }
+        EitherOrBoth::Both(old_pkg, new_pkg) => {
+          (ChangeKind::Downgraded, Some(old_pkg), Some(new_pkg))
+        }
+        EitherOrBoth::Left(old_pkg) => (ChangeKind::Removed, Some(old_pkg), None),
+        EitherOrBoth::Right(new_pkg) => (ChangeKind::Added, None, Some(new_pkg)),
+      };
+      changes.push(LockfileChange {
+        name: name.clone(),
+        kind,
+        old: old_pkg.cloned(),
+        new: new_pkg.cloned(),
+      });
+    }
+  }
+
+  changes
+}
+
+/// Renders lockfile changes as a Markdown changelog, grouped by the kind of change.
+pub fn render_lockfile_changelog(changes: &[LockfileChange]) -> String {
+  if changes.is_empty() {
+    return "No dependencies changed.\n".to_owned();
+  }
+
+  let mut output = "# Dependency changes\n".to_owned();
+  let groups = changes.iter().into_group_map_by(|change| change.kind);
+  for (kind, title) in [
+    (ChangeKind::Added, "Added"),
+    (ChangeKind::Removed, "Removed"),
+    (ChangeKind::Upgraded, "Upgraded"),
+    (ChangeKind::Downgraded, "Downgraded"),
+    (ChangeKind::Modified, "Modified"),
+  ] {
+    let group = match groups.get(&kind) {
+      Some(group) => group,
+      None => continue,
+    };
+
+    output.push_str(&format!("\n## {}\n\n", title));
+    for change in group {
+      output.push_str(&format!("- {}\n", render_change(change)));
+    }
+  }
+
+  output
+}
+
+fn render_change(change: &LockfileChange) -> String {
+  match (&change.old, &change.new) {
+    (Some(old), Some(new)) => {
+      let mut rendered = if old.version == new.version {
+        format!("`{}` {}", change.name, new.version)
+      } else {
+        format!("`{}` {} → {}", change.name, old.version, new.version)
+      };
+      if old.source != new.source {
+        rendered.push_str(&format!(
+          ", source: {} → {}",
+          render_optional(&old.source),
+          render_optional(&new.source)
+        ));
+      }
+      // Checksums always change along with the version, so they are only noteworthy otherwise
+      if old.version == new.version && old.checksum != new.checksum {
+        rendered.push_str(&format!(
+          ", checksum: {} → {}",
+          render_optional(&old.checksum),
+          render_optional(&new.checksum)
+        ));
+      }
+      rendered
+    }
+    (Some(package), None) | (None, Some(package)) => {
+      let mut rendered = format!("`{}` {}", change.name, package.version);
+      if let Some(source) = &package.source {
+        rendered.push_str(&format!(", source: {}", source));
+      }
+      rendered
+    }
+    (None, None) => format!("`{}`", change.name),
+  }
+}
+
+fn render_optional(value: &Option<String>) -> &str {
+  value.as_deref().unwrap_or("none")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::str::FromStr;
+
+  use indoc::indoc;
+
+  use super::*;
+  use crate::testing::advanced_lock_contents;
+
+  const GIT_SOURCE: &str =
+    "git+https://github.com/dtolnay/proc-macro2?rev=1e0704e#1e0704ee1a7e00d7bb417d0770ea303c1bccbabf";
+
+  fn new_lock_contents() -> String {
+    format!(
+      indoc! { r#"
+        [[package]]
+        name = "cargo-raze-test"
+        version = "0.1.0"
+        dependencies = [
+          "proc-macro2",
+          "unicode-xid 0.1.0",
+        ]
+
+        [[package]]
+        name = "proc-macro2"
+        version = "1.0.24"
+        source = "{git_source}"
+        dependencies = [
+          "unicode-xid 0.2.2",
+        ]
+
+        [[package]]
+        name = "unicode-xid"
+        version = "0.1.0"
+        source = "registry+https://github.com/rust-lang/crates.io-index"
+        checksum = "fc72304796d0818e357ead4e000d19c9c174ab23dc11093ac919054d20a6a7fc"
+
+        [[package]]
+        name = "unicode-xid"
+        version = "0.2.2"
+        source = "registry+https://github.com/rust-lang/crates.io-index"
+        checksum = "8ccb82d61f80a663efe1f787a51b16b5a51e3314d6ac365b08639f52387b33f3"
+      "# },
+      git_source = GIT_SOURCE
+    )
+  }
+
+  #[test]
+  fn test_diff_lockfiles() {
+    let old = Lockfile::from_str(advanced_lock_contents()).unwrap();
+    let new = Lockfile::from_str(&new_lock_contents()).unwrap();
+
+    let changes = diff_lockfiles(&old, &new);
+    assert_eq!(
+      changes
+        .iter()
+        .map(|change| (change.name.as_str(), change.kind))
+        .collect_vec(),
+      vec![
+        ("proc-macro2", ChangeKind::Modified),
+        ("unicode-xid", ChangeKind::Upgraded),
+        ("unicode-xid", ChangeKind::Added),
+      ]
+    );
+
+    let proc_macro2 = &changes[0];
+    assert_eq!(
+      proc_macro2.new.as_ref().unwrap().source.as_deref(),
+      Some(GIT_SOURCE)
+    );
+    assert_eq!(proc_macro2.new.as_ref().unwrap().checksum, None);
+
+    // The newest remaining versions are paired, leaving the oldest one added
+    assert_eq!(
+      changes[1].old.as_ref().unwrap().version,
+      Version::new(0, 2, 1)
+    );
+    assert_eq!(
+      changes[1].new.as_ref().unwrap().version,
+      Version::new(0, 2, 2)
+    );
+    assert_eq!(
+      changes[2].new.as_ref().unwrap().version,
+      Version::new(0, 1, 0)
+    );
+  }
+
+  #[test]
+  fn test_diff_identical_lockfiles() {
+    let lockfile = Lockfile::from_str(advanced_lock_contents()).unwrap();
+    let changes = diff_lockfiles(&lockfile, &lockfile);
+    assert!(changes.is_empty());
+    assert_eq!(
+      render_lockfile_changelog(&changes),
+      "No dependencies changed.\n"
+    );
+  }
+
+  #[test]
+  fn test_render_lockfile_changelog() {
+    let old = Lockfile::from_str(advanced_lock_contents()).unwrap();
+    let new = Lockfile::from_str(&new_lock_contents()).unwrap();
+
+    let changelog = render_lockfile_changelog(&diff_lockfiles(&old, &new));
+    assert!(changelog.starts_with("# Dependency changes\n\n## Added\n\n- `unicode-xid` 0.1.0"));
+    assert!(changelog.contains("\n## Upgraded\n\n- `unicode-xid` 0.2.1 → 0.2.2\n"));
+    assert!(changelog.contains(&format!(
+      "- `proc-macro2` 1.0.24, source: registry+https://github.com/rust-lang/crates.io-index → {}, \
+       checksum: 1e0704ee1a7e00d7bb417d0770ea303c1bccbabf0ef1667dae92b5967f5f8a71 → none\n",
+      GIT_SOURCE
+    )));
+  }
+}
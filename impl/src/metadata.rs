@@ -13,27 +13,32 @@
 // limitations under the License.
 
 use std::{
-  collections::{BTreeMap, HashMap},
+  collections::{BTreeMap, HashMap, HashSet},
   env::consts,
   fs,
+  str::FromStr,
   string::String,
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use cargo_lock::Lockfile;
-use cargo_metadata::{Metadata, MetadataCommand, PackageId};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, PackageId, Source};
 use glob::glob;
 use pathdiff::diff_paths;
 use regex::Regex;
 use rustc_serialize::hex::ToHex;
+use semver::Version;
+use sha2::{Digest, Sha256};
 use tempfile::TempDir;
 use url::Url;
 
-use crate::util::{cargo_bin_path, package_ident};
+use crate::util::{cargo_accepts_unstable_flags, cargo_bin_path, package_ident};
 use crate::{
+  error::RazeError,
   features::{get_per_platform_features, Features},
-  settings::RazeSettings,
+  registry::RegistryIndex,
+  settings::{RazeSettings, VersionResolution},
 };
 
 pub(crate) const DEFAULT_CRATE_REGISTRY_URL: &str = "https://crates.io";
@@ -47,12 +52,14 @@ pub trait MetadataFetcher {
 /// A lockfile generator which simply wraps the `cargo_metadata::MetadataCommand` command
 struct CargoMetadataFetcher {
   pub cargo_bin_path: Utf8PathBuf,
+  pub offline: bool,
 }
 
 impl Default for CargoMetadataFetcher {
   fn default() -> CargoMetadataFetcher {
     CargoMetadataFetcher {
       cargo_bin_path: cargo_bin_path(),
+      offline: false,
     }
   }
 }
@@ -65,6 +72,10 @@ impl MetadataFetcher for CargoMetadataFetcher {
       command.no_deps();
     }
 
+    if self.offline {
+      command.other_options(vec!["--offline".to_owned()]);
+    }
+
     command
       .cargo_path(&self.cargo_bin_path)
       .current_dir(working_dir)
@@ -81,11 +92,45 @@ impl MetadataFetcher for CargoMetadataFetcher {
 /// An entity that can generate a lockfile data within a Cargo workspace
 pub trait LockfileGenerator {
   fn generate_lockfile(&self, crate_root_dir: &Utf8Path) -> Result<Lockfile>;
+
+  /// Updates only the given crates in the existing lockfile of a Cargo workspace
+  fn update_lockfile(&self, crate_root_dir: &Utf8Path, updates: &[CrateUpdate])
+    -> Result<Lockfile>;
+}
+
+/// A crate to update in an existing lockfile, parsed from `<crate>[@<version>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateUpdate {
+  pub name: String,
+  /// The exact version to update to, rather than the newest compatible one
+  pub precise: Option<Version>,
+}
+
+impl FromStr for CrateUpdate {
+  type Err = anyhow::Error;
+
+  fn from_str(spec: &str) -> Result<Self> {
+    match spec.split_once('@') {
+      Some((name, version)) => Ok(CrateUpdate {
+        name: name.to_owned(),
+        precise: Some(
+          Version::parse(version)
+            .with_context(|| format!("Invalid version in crate update `{}`", spec))?,
+        ),
+      }),
+      None => Ok(CrateUpdate {
+        name: spec.to_owned(),
+        precise: None,
+      }),
+    }
+  }
 }
 
 /// A lockfile generator which simply wraps the `cargo generate-lockfile` command
 struct CargoLockfileGenerator {
   cargo_bin_path: Utf8PathBuf,
+  offline: bool,
+  version_resolution: VersionResolution,
 }
 
 impl LockfileGenerator for CargoLockfileGenerator {
@@ -94,8 +139,31 @@ impl LockfileGenerator for CargoLockfileGenerator {
     let lockfile_path = crate_root_dir.join("Cargo.lock");
 
     // Generate lockfile
-    let output = std::process::Command::new(&self.cargo_bin_path)
-      .arg("generate-lockfile")
+    let mut command = std::process::Command::new(&self.cargo_bin_path);
+    command.arg("generate-lockfile");
+    if self.offline {
+      command.arg("--offline");
+    }
+    // These flags are unstable and only accepted by nightly toolchains
+    let unstable_flag = match self.version_resolution {
+      VersionResolution::Maximal => None,
+      VersionResolution::Minimal => Some("minimal-versions"),
+      VersionResolution::DirectMinimal => Some("direct-minimal-versions"),
+    };
+    if let Some(unstable_flag) = unstable_flag {
+      if !cargo_accepts_unstable_flags(&self.cargo_bin_path) {
+        bail!(
+          "`version_resolution = \"{:?}\"` requires `-Z {}`, which {} does not accept. Use a \
+           nightly toolchain or set `RUSTC_BOOTSTRAP=1`.",
+          self.version_resolution,
+          unstable_flag,
+          self.cargo_bin_path
+        );
+      }
+      command.args(["-Z", unstable_flag]);
+    }
+
+    let output = command
       .current_dir(crate_root_dir)
       .output()
       .with_context(|| format!("Generating lockfile in {}", crate_root_dir))?;
@@ -112,6 +180,44 @@ impl LockfileGenerator for CargoLockfileGenerator {
     Lockfile::load(&lockfile_path)
       .with_context(|| format!("Failed to load lockfile: {}", lockfile_path))
   }
+
+  /// Run `cargo update -p` for each crate to update in a cargo workspace root
+  fn update_lockfile(
+    &self,
+    crate_root_dir: &Utf8Path,
+    updates: &[CrateUpdate],
+  ) -> Result<Lockfile> {
+    let lockfile_path = crate_root_dir.join("Cargo.lock");
+
+    // Crates updated to a precise version each need their own invocation
+    for update in updates {
+      let mut command = std::process::Command::new(&self.cargo_bin_path);
+      command.args(["update", "-p", &update.name]);
+      if let Some(precise) = &update.precise {
+        command.args(["--precise", &precise.to_string()]);
+      }
+      if self.offline {
+        command.arg("--offline");
+      }
+
+      let output = command
+        .current_dir(crate_root_dir)
+        .output()
+        .with_context(|| format!("Updating `{}` in {}", update.name, crate_root_dir))?;
+
+      if !output.status.success() {
+        anyhow::bail!(
+          "Failed to update `{}` in {}: {}",
+          update.name,
+          crate_root_dir,
+          String::from_utf8_lossy(&output.stderr)
+        );
+      }
+    }
+
+    Lockfile::load(&lockfile_path)
+      .with_context(|| format!("Failed to load lockfile: {}", lockfile_path))
+  }
 }
 
 /// A struct containing all metadata about a project with which to plan generated output files for
@@ -133,6 +239,21 @@ pub struct RazeMetadata {
 
   // A map of crates to their enabled general and per-platform features.
   pub features: BTreeMap<PackageId, Features>,
+
+  // A map of binary dependency names to the sources of those fetched from git repositories or
+  // alternate registries. Other binary dependencies come from the configured registry.
+  pub binary_dep_sources: HashMap<String, BinaryDependencySource>,
+}
+
+/// The source of a binary dependency which Cargo fetched from a git repository or an alternate
+/// registry.
+#[derive(Debug, Clone)]
+pub struct BinaryDependencySource {
+  /// The source Cargo resolved the dependency to, eg: `git+https://...?rev=...#{commit}`
+  pub source: Source,
+  /// The directory containing the crate's Cargo.toml, relative to the root of its git repository.
+  /// None if the crate is not from a git repository or lives at its root.
+  pub path_to_crate_root: Option<String>,
 }
 
 impl RazeMetadata {
@@ -142,6 +263,147 @@ impl RazeMetadata {
   }
 }
 
+/// The directory in the temp workspace where binary dependencies are resolved by Cargo
+const BINARY_DEPS_SCRATCH_DIR: &str = ".raze-binary-deps";
+
+/// Returns true if the binary dependency is fetched by Cargo instead of from the configured
+/// registry, as it comes from a git repository or an alternate registry.
+fn is_fetched_with_cargo(info: &cargo_toml::Dependency) -> bool {
+  match info {
+    cargo_toml::Dependency::Detailed(detailed) => {
+      detailed.git.is_some() || detailed.registry.is_some() || detailed.registry_index.is_some()
+    }
+    cargo_toml::Dependency::Simple(_) => false,
+  }
+}
+
+/// Finds the directory of a crate within Cargo's checkout of the git repository it was fetched
+/// from, as vendoring only keeps the crate's own directory. None if it lives at the root.
+///
+/// `cargo metadata` ignores dependencies without a library target, so the checkout is taken from
+/// the status `cargo vendor` printed for the crate: `Vendoring {name} v{version} ({source})
+/// ({checkout path}) to {vendor path}`.
+fn find_path_to_crate_root_in_git_checkout(
+  vendor_stderr: &str,
+  name: &str,
+  version: &str,
+) -> Result<Option<String>> {
+  let status_prefix = format!("Vendoring {} v{} (", name, version);
+  let crate_dir = vendor_stderr
+    .lines()
+    .filter_map(|line| line.trim().strip_prefix(&status_prefix))
+    .filter_map(|status| status.rsplit_once(") to ").map(|(status, _)| status))
+    .find_map(|status| {
+      status
+        .rsplit_once(" (")
+        .map(|(_, path)| Utf8PathBuf::from(path))
+    })
+    .ok_or_else(|| {
+      anyhow!(
+        "Failed to find the git checkout of {} in the output of `cargo vendor`:\n{}",
+        package_ident(name, version),
+        vendor_stderr
+      )
+    })?;
+
+  let checkout_root = crate_dir
+    .ancestors()
+    .find(|dir| dir.join(".git").exists())
+    .ok_or_else(|| anyhow!("Unable to locate git repository root of {}", crate_dir))?;
+  let crate_root = crate_dir.strip_prefix(checkout_root)?;
+  Ok(Some(crate_root.to_string()).filter(|path| !path.is_empty()))
+}
+
+/// Ensures the `.cargo-checksum.json` file of each crate vendored by `cargo vendor` records the
+/// checksum the lockfile has for it, and that the crate's files match the sha256 checksums recorded
+/// for them, catching vendored sources which are stale, edited or corrupted. Workspace and path
+/// crates are not vendored and are skipped, as is the package checksum of crates from git, which
+/// have none.
+fn verify_vendored_checksums(lockfile: &Lockfile, vendor_dir: &Utf8Path) -> Result<()> {
+  let mut mismatched_crates = Vec::new();
+  let mut mismatched_files = Vec::new();
+  for package in lockfile.packages.iter().filter(|pkg| pkg.source.is_some()) {
+    let ident = package_ident(package.name.as_str(), &package.version.to_string());
+    let crate_dir = vendor_dir.join(&ident);
+    let checksum_path = crate_dir.join(".cargo-checksum.json");
+    let checksum_file: serde_json::Value = serde_json::from_str(
+      &fs::read_to_string(&checksum_path)
+        .with_context(|| format!("Failed to read {}", checksum_path))?,
+    )
+    .with_context(|| format!("Failed to parse {}", checksum_path))?;
+
+    if let Some(checksum) = &package.checksum {
+      if checksum_file["package"].as_str() != Some(checksum.to_string().as_str()) {
+        mismatched_crates.push(ident);
+      }
+    }
+
+    let files = checksum_file["files"]
+      .as_object()
+      .ok_or_else(|| anyhow!("{} has no `files` checksums", checksum_path))?;
+
+    for (file, checksum) in files {
+      let actual_checksum = fs::read(crate_dir.join(file))
+        .ok()
+        .map(|contents| Sha256::digest(&contents).to_hex());
+      if actual_checksum.as_deref() != checksum.as_str() {
+        mismatched_files.push(crate_dir.join(file).to_string());
+      }
+    }
+  }
+
+  if !mismatched_crates.is_empty() {
+    bail!(
+      "The checksums of vendored crates in {} do not match the lockfile: {:?}",
+      vendor_dir,
+      mismatched_crates
+    );
+  }
+
+  if !mismatched_files.is_empty() {
+    mismatched_files.sort();
+    bail!(
+      "The contents of vendored crates in {} do not match their `.cargo-checksum.json` files: \
+       {:?}",
+      vendor_dir,
+      mismatched_files
+    );
+  }
+
+  Ok(())
+}
+
+/// Reads the checksum of a vendored crate's `.crate` file from its `.cargo-checksum.json`. Crates
+/// from git have none.
+fn read_vendored_package_checksum(crate_dir: &Utf8Path) -> Result<Option<String>> {
+  let checksum_path = crate_dir.join(".cargo-checksum.json");
+  let checksum_file: serde_json::Value = serde_json::from_str(
+    &fs::read_to_string(&checksum_path)
+      .with_context(|| format!("Failed to read {}", checksum_path))?,
+  )
+  .with_context(|| format!("Failed to parse {}", checksum_path))?;
+  Ok(checksum_file["package"].as_str().map(str::to_owned))
+}
+
+/// Recursively copies a crate's sources, skipping any git or build output directories
+fn copy_crate_dir(src: &Utf8Path, dest: &Utf8Path) -> Result<()> {
+  fs::create_dir_all(dest)?;
+  for entry in src.read_dir_utf8()? {
+    let entry = entry?;
+    let dest_path = dest.join(entry.file_name());
+    if entry.file_type()?.is_dir() {
+      if entry.file_name() != ".git" && entry.file_name() != "target" {
+        copy_crate_dir(entry.path(), &dest_path)?;
+      }
+    } else {
+      fs::copy(entry.path(), &dest_path)
+        .with_context(|| format!("Failed to copy {} to {}", entry.path(), dest_path))?;
+    }
+  }
+
+  Ok(())
+}
+
 /// Create a symlink file on unix systems
 #[cfg(target_family = "unix")]
 fn make_symlink(src: &Utf8Path, dest: &Utf8Path) -> Result<()> {
@@ -159,11 +421,16 @@ fn make_symlink(src: &Utf8Path, dest: &Utf8Path) -> Result<()> {
 /// A workspace metadata fetcher that uses the Cargo commands to gather information about a Cargo
 /// project and it's transitive dependencies for planning and rendering of Bazel BUILD files.
 pub struct RazeMetadataFetcher {
+  cargo_bin_path: Utf8PathBuf,
   registry_url: Url,
   index_url: Url,
   metadata_fetcher: Box<dyn MetadataFetcher>,
   lockfile_generator: Box<dyn LockfileGenerator>,
   settings: Option<RazeSettings>,
+  offline: bool,
+  vendor_dir: Option<Utf8PathBuf>,
+  update_vendor_dir: bool,
+  crate_updates: Vec<CrateUpdate>,
 }
 
 impl RazeMetadataFetcher {
@@ -174,16 +441,29 @@ impl RazeMetadataFetcher {
     settings: Option<RazeSettings>,
   ) -> RazeMetadataFetcher {
     let cargo_bin_pathbuf: Utf8PathBuf = cargo_bin_path.into();
+    let offline = matches!(&settings, Some(settings) if settings.offline);
+    let version_resolution = settings
+      .as_ref()
+      .map(|settings| settings.version_resolution)
+      .unwrap_or_default();
     RazeMetadataFetcher {
+      cargo_bin_path: cargo_bin_pathbuf.clone(),
       registry_url,
       index_url,
       metadata_fetcher: Box::new(CargoMetadataFetcher {
         cargo_bin_path: cargo_bin_pathbuf.clone(),
+        offline,
       }),
       lockfile_generator: Box::new(CargoLockfileGenerator {
         cargo_bin_path: cargo_bin_pathbuf,
+        offline,
+        version_resolution,
       }),
       settings,
+      offline,
+      vendor_dir: None,
+      update_vendor_dir: false,
+      crate_updates: Vec::new(),
     }
   }
 
@@ -207,6 +487,23 @@ impl RazeMetadataFetcher {
     self.lockfile_generator = generator;
   }
 
+  /// Set the directory crates are vendored into. This is used in `GenMode::Vendored`.
+  ///
+  /// Binary dependencies from the configured registry which are already vendored there are not
+  /// fetched again. If `update` is true, the binary dependencies and their dependencies which are
+  /// missing are vendored into it with `cargo vendor`. If `run_cargo_vendor` is also set, all other
+  /// crates are vendored into it as well.
+  pub fn set_vendor_dir(&mut self, vendor_dir: Utf8PathBuf, update: bool) {
+    self.vendor_dir = Some(vendor_dir);
+    self.update_vendor_dir = update;
+  }
+
+  /// Set the crates to update in a reused lockfile, leaving all other crates at their locked
+  /// versions.
+  pub fn set_crate_updates(&mut self, crate_updates: Vec<CrateUpdate>) {
+    self.crate_updates = crate_updates;
+  }
+
   /// Symlinks the source code of all workspace members into the temp workspace
   fn link_src_to_workspace(&self, no_deps_metadata: &Metadata, temp_dir: &Utf8Path) -> Result<()> {
     let crate_member_id_re = match consts::OS {
@@ -361,6 +658,258 @@ impl RazeMetadataFetcher {
     Ok(crate_dir)
   }
 
+  /// Fetch the sources of a binary dependency from a git repository or an alternate registry by
+  /// vendoring it as the only dependency of a scratch package. The sources are moved into `dir`
+  /// and the checksum of crates from registries is added to `checksums`.
+  ///
+  /// `cargo metadata` is not used as it ignores dependencies without a library target.
+  fn fetch_crate_src_with_cargo(
+    &self,
+    dir: &Utf8Path,
+    name: &str,
+    info: &cargo_toml::Dependency,
+    checksums: &mut HashMap<String, String>,
+  ) -> Result<(Utf8PathBuf, BinaryDependencySource)> {
+    // The scratch package is kept inside the temp workspace so it shares its `.cargo/config.toml`
+    let scratch_dir = dir.join(BINARY_DEPS_SCRATCH_DIR);
+    fs::create_dir_all(&scratch_dir)?;
+    fs::write(scratch_dir.join("lib.rs"), "")?;
+    fs::write(
+      scratch_dir.join("Cargo.toml"),
+      format!(
+        "[package]\nname = \"raze-binary-dependency\"\nversion = \"0.0.0\"\n\n[lib]\npath = \
+         \"lib.rs\"\n\n[workspace]\n\n[dependencies.{}]\n{}",
+        name,
+        toml::to_string(info)?
+      ),
+    )?;
+
+    log::debug!("Fetching binary dependency with cargo: {}", &name);
+    let mut command = std::process::Command::new(&self.cargo_bin_path);
+    command.args(["vendor", "--versioned-dirs"]);
+    if self.offline {
+      command.arg("--offline");
+    }
+    let output = command
+      .arg("vendor")
+      .current_dir(&scratch_dir)
+      .output()
+      .with_context(|| format!("Fetching binary dependency {}", name))?;
+    if !output.status.success() {
+      bail!(
+        "Failed to fetch binary dependency {}: {}",
+        name,
+        String::from_utf8_lossy(&output.stderr)
+      );
+    }
+
+    // The lockfile is read as plain TOML as `cargo_lock` does not understand sparse sources
+    let lockfile: toml::Value = fs::read_to_string(scratch_dir.join("Cargo.lock"))?.parse()?;
+    let locked_package = lockfile
+      .get("package")
+      .and_then(toml::Value::as_array)
+      .into_iter()
+      .flatten()
+      .find(|pkg| pkg.get("name").and_then(toml::Value::as_str) == Some(name))
+      .ok_or_else(|| {
+        anyhow!(
+          "Failed to find binary dependency `{}` in its lockfile",
+          name
+        )
+      })?;
+    let locked_field = |field: &str| locked_package.get(field).and_then(toml::Value::as_str);
+    let (version, source) = match (locked_field("version"), locked_field("source")) {
+      (Some(version), Some(source)) => (version.to_owned(), source.to_owned()),
+      _ => bail!(
+        "Expected binary dependency `{}` to have a version and source",
+        name
+      ),
+    };
+    if let Some(checksum) = locked_field("checksum") {
+      checksums.insert(package_ident(name, &version), checksum.to_owned());
+    }
+
+    let path_to_crate_root = match source.strip_prefix("git+") {
+      Some(_) => find_path_to_crate_root_in_git_checkout(
+        &String::from_utf8_lossy(&output.stderr),
+        name,
+        &version,
+      )?,
+      None => None,
+    };
+
+    let src_dir = dir.join(package_ident(name, &version));
+    fs::rename(
+      scratch_dir
+        .join("vendor")
+        .join(package_ident(name, &version)),
+      &src_dir,
+    )?;
+    fs::remove_dir_all(&scratch_dir)?;
+
+    Ok((
+      src_dir,
+      BinaryDependencySource {
+        source: Source { repr: source },
+        path_to_crate_root,
+      },
+    ))
+  }
+
+  /// Returns the directory a binary dependency from the configured registry is vendored in, if it
+  /// already is. Those from git or alternate registries are always fetched, as their version is
+  /// only known once Cargo resolves them.
+  fn find_vendored_binary_dep(
+    &self,
+    name: &str,
+    info: &cargo_toml::Dependency,
+  ) -> Option<Utf8PathBuf> {
+    if is_fetched_with_cargo(info) {
+      return None;
+    }
+
+    let crate_dir = self
+      .vendor_dir
+      .as_ref()?
+      .join(package_ident(name, info.req()));
+    Some(crate_dir).filter(|dir| dir.join(".cargo-checksum.json").exists())
+  }
+
+  /// Vendors binary dependencies and their transitive dependencies into the vendor directory with
+  /// `cargo vendor --versioned-dirs`. Binary dependencies are workspace members of the temp
+  /// workspace, which `cargo vendor` skips, so they are vendored as the dependencies of a scratch
+  /// package resolved with the temp workspace's lockfile. Crates which are already vendored are
+  /// left untouched.
+  fn vendor_binary_deps(
+    &self,
+    cargo_dir: &Utf8Path,
+    metadata: &Metadata,
+    binary_dep_info: &HashMap<String, cargo_toml::Dependency>,
+    vendor_dir: &Utf8Path,
+  ) -> Result<()> {
+    let resolve = metadata
+      .resolve
+      .as_ref()
+      .ok_or_else(|| anyhow!("Missing resolve graph"))?;
+
+    // Binary dependencies are workspace members, so start from those
+    let mut pending: Vec<&PackageId> = metadata
+      .packages
+      .iter()
+      .filter(|pkg| binary_dep_info.contains_key(&pkg.name))
+      .filter(|pkg| metadata.workspace_members.contains(&pkg.id))
+      .map(|pkg| &pkg.id)
+      .collect();
+    let mut visited: HashSet<&PackageId> = HashSet::new();
+    while let Some(id) = pending.pop() {
+      if !visited.insert(id) {
+        continue;
+      }
+
+      if let Some(node) = resolve.nodes.iter().find(|node| node.id == *id) {
+        pending.extend(
+          node
+            .deps
+            .iter()
+            .filter(|dep| {
+              dep
+                .dep_kinds
+                .iter()
+                .any(|info| info.kind != DependencyKind::Development)
+            })
+            .map(|dep| &dep.pkg),
+        );
+      }
+    }
+
+    let find_missing_crates = || -> Vec<String> {
+      let mut missing_crates: Vec<String> = metadata
+        .packages
+        .iter()
+        .filter(|pkg| visited.contains(&pkg.id))
+        .map(|pkg| package_ident(&pkg.name, &pkg.version.to_string()))
+        .filter(|ident| !vendor_dir.join(ident).exists())
+        .collect();
+      missing_crates.sort();
+      missing_crates
+    };
+    if find_missing_crates().is_empty() {
+      return Ok(());
+    }
+
+    let mut dependencies = toml::value::Table::new();
+    for (name, info) in binary_dep_info.iter() {
+      let dependency = if is_fetched_with_cargo(info) {
+        toml::Value::try_from(info)?
+      } else {
+        // Binary dependencies from the configured registry are always of an exact version
+        toml::Value::String(format!("={}", info.req()))
+      };
+      dependencies.insert(name.clone(), dependency);
+    }
+
+    let mut manifest = toml::value::Table::new();
+    manifest.insert("dependencies".to_owned(), toml::Value::Table(dependencies));
+
+    // The scratch package is kept inside the temp workspace so it shares its `.cargo/config.toml`
+    let scratch_dir = cargo_dir.join(BINARY_DEPS_SCRATCH_DIR);
+    fs::create_dir_all(&scratch_dir)?;
+    fs::write(scratch_dir.join("lib.rs"), "")?;
+    fs::write(
+      scratch_dir.join("Cargo.toml"),
+      format!(
+        "[package]\nname = \"raze-binary-dependencies\"\nversion = \"0.0.0\"\n\n[lib]\npath = \
+         \"lib.rs\"\n\n[workspace]\n\n{}",
+        toml::to_string(&manifest)?
+      ),
+    )?;
+    // Starting from the temp workspace's lockfile keeps the versions which were planned
+    fs::copy(cargo_dir.join("Cargo.lock"), scratch_dir.join("Cargo.lock"))?;
+
+    self.cargo_vendor(&scratch_dir, vendor_dir, /*locked=*/ false)?;
+    fs::remove_dir_all(&scratch_dir)?;
+
+    let missing_crates = find_missing_crates();
+    if !missing_crates.is_empty() {
+      bail!(
+        "Vendoring binary dependencies did not produce the planned crates: {}",
+        missing_crates.join(", ")
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Vendors the dependencies of a cargo workspace into `vendor_dir` with `cargo vendor`, using
+  /// versioned directories as expected by `GenMode::Vendored`. Crates which are already in
+  /// `vendor_dir` are kept, as are any others, such as binary dependencies vendored separately.
+  fn cargo_vendor(&self, cargo_dir: &Utf8Path, vendor_dir: &Utf8Path, locked: bool) -> Result<()> {
+    let mut command = std::process::Command::new(&self.cargo_bin_path);
+    command.args(["vendor", "--versioned-dirs", "--no-delete", "-q"]);
+    if locked {
+      command.arg("--locked");
+    }
+    if self.offline {
+      command.arg("--offline");
+    }
+    command.arg(vendor_dir);
+
+    let output = command
+      .current_dir(cargo_dir)
+      .output()
+      .with_context(|| format!("Vendoring crates into {}", vendor_dir))?;
+
+    if !output.status.success() {
+      bail!(
+        "Failed to vendor crates into {}: {}",
+        vendor_dir,
+        String::from_utf8_lossy(&output.stderr)
+      );
+    }
+
+    Ok(())
+  }
+
   /// Add binary dependencies as workspace members to the given workspace root Cargo.toml file
   fn inject_binaries_into_workspace(
     &self,
@@ -395,28 +944,41 @@ impl RazeMetadataFetcher {
       .with_context(|| format!("Failed to inject workspace metadata to {}", root_toml))
   }
 
-  /// Look up a crate in a specified crate index to determine it's checksum
-  fn fetch_crate_checksum(&self, name: &str, version: &str) -> Result<String> {
-    let index_url_is_file = self.index_url.scheme().to_lowercase() == "file";
-    let crate_index_path = if !index_url_is_file {
-      crates_index::BareIndex::from_url(self.index_url.as_ref())?
-        .open_or_clone()?
-        .crate_(name)
-        .ok_or_else(|| anyhow!("Failed to find crate '{}' in index", name))?
-    } else {
-      crates_index::Index::new(self.index_url.path())
-        .crate_(name)
-        .ok_or_else(|| anyhow!("Failed to find crate '{}' in index", name))?
-    };
+  /// Returns true if the crate index can be read without network access
+  fn index_is_local(&self) -> bool {
+    RegistryIndex::from_url(self.index_url.as_str())
+      .map(|index| index.is_local())
+      .unwrap_or(false)
+  }
 
-    let (_index, crate_version) = crate_index_path
-      .versions()
-      .iter()
-      .enumerate()
-      .find(|(_, ver)| ver.version() == version)
-      .ok_or_else(|| anyhow!("Failed to find version {} for crate {}", version, name))?;
+  /// Lists the network operations needed to fetch the given binary dependencies from the
+  /// configured registry.
+  fn binary_deps_network_operations(
+    &self,
+    binary_dep_info: &HashMap<String, cargo_toml::Dependency>,
+  ) -> Vec<String> {
+    let mut operations = Vec::new();
+    for (name, info) in binary_dep_info.iter() {
+      let ident = package_ident(name, info.req());
+      operations.push(format!(
+        "Downloading the sources of binary dependency `{}` from {}",
+        ident, self.registry_url
+      ));
+      if !self.index_is_local() {
+        operations.push(format!(
+          "Fetching the crate index {} to look up the checksum of `{}`",
+          self.index_url, ident
+        ));
+      }
+    }
 
-    Ok(crate_version.checksum()[..].to_hex())
+    operations.sort();
+    operations
+  }
+
+  /// Look up a crate in a specified crate index to determine it's checksum
+  fn fetch_crate_checksum(&self, name: &str, version: &str) -> Result<String> {
+    RegistryIndex::from_url(self.index_url.as_str())?.checksum(name, version)
   }
 
   /// Ensures a lockfile is generated for a crate on disk
@@ -428,7 +990,7 @@ impl RazeMetadataFetcher {
   /// Returns:
   ///   If a new lockfile was generated via the `lockfile_generator`, that
   ///   Lockfile object is returned. New lockfiles are generated when
-  ///   `reused_lockfile` is not provided.
+  ///   `reused_lockfile` is not provided, or when any crates are to be updated.
   fn cargo_generate_lockfile(
     &self,
     reused_lockfile: &Option<Utf8PathBuf>,
@@ -438,11 +1000,51 @@ impl RazeMetadataFetcher {
 
     // Use the reusable lockfile if one is provided
     if let Some(reused_lockfile) = reused_lockfile {
+      if let Some(settings) = &self.settings {
+        if settings.version_resolution != VersionResolution::default() {
+          eprintln!(
+            "WARNING: `version_resolution` only applies to new lockfiles and is ignored as {} is \
+             reused. Pass `--generate-lockfile` to apply it.",
+            reused_lockfile
+          );
+        }
+        if !settings.precise_versions.is_empty() {
+          eprintln!(
+            "WARNING: `precise_versions` only applies to new lockfiles and is ignored as {} is \
+             reused. Pass `--generate-lockfile` or `--update=<name>@<version>` to apply it.",
+            reused_lockfile
+          );
+        }
+      }
       fs::copy(reused_lockfile, &lockfile_path)?;
-      return Ok(None);
+      if self.crate_updates.is_empty() {
+        return Ok(None);
+      }
+
+      let lockfile = self
+        .lockfile_generator
+        .update_lockfile(cargo_dir, &self.crate_updates)?;
+      return Ok(Some(lockfile));
     }
 
-    let lockfile = self.lockfile_generator.generate_lockfile(cargo_dir)?;
+    let mut lockfile = self.lockfile_generator.generate_lockfile(cargo_dir)?;
+
+    // Pinned versions are applied to every new lockfile, before any requested updates
+    let updates = self
+      .settings
+      .iter()
+      .flat_map(|settings| settings.precise_versions.iter())
+      .map(|(name, version)| CrateUpdate {
+        name: name.clone(),
+        precise: Some(version.clone()),
+      })
+      .chain(self.crate_updates.iter().cloned())
+      .collect::<Vec<_>>();
+    if !updates.is_empty() {
+      lockfile = self
+        .lockfile_generator
+        .update_lockfile(cargo_dir, &updates)?;
+    }
 
     // Returning the lockfile here signifies that a new lockfile has been created.
     Ok(Some(lockfile))
@@ -462,17 +1064,49 @@ impl RazeMetadataFetcher {
 
     // Gather new lockfile data if any binary dependencies were provided
     let mut checksums: HashMap<String, String> = HashMap::new();
+    let mut binary_dep_sources: HashMap<String, BinaryDependencySource> = HashMap::new();
     if let Some(binary_dep_info) = binary_dep_info {
+      // Cargo fetches the others offline if it has done so before, failing otherwise
+      let downloaded_binary_deps: HashMap<String, cargo_toml::Dependency> = binary_dep_info
+        .iter()
+        .filter(|(_, info)| !is_fetched_with_cargo(info))
+        .filter(|(name, info)| self.find_vendored_binary_dep(name, info).is_none())
+        .map(|(name, info)| (name.clone(), info.clone()))
+        .collect();
+      if self.offline && !downloaded_binary_deps.is_empty() {
+        return Err(
+          RazeError::Offline {
+            operations: self.binary_deps_network_operations(&downloaded_binary_deps),
+          }
+          .into(),
+        );
+      }
+
       if !binary_dep_info.is_empty() {
         let mut src_dirnames: Vec<String> = Vec::new();
 
         for (name, info) in binary_dep_info.iter() {
-          let version = info.req();
-          let src_dir = self.fetch_crate_src(utf8_cargo_dir, name, version)?;
-          checksums.insert(
-            package_ident(name, version),
-            self.fetch_crate_checksum(name, version)?,
-          );
+          let src_dir = if let Some(vendored_dir) = self.find_vendored_binary_dep(name, info) {
+            // UNWRAP: Vendored crate directories are always named after the crate
+            let src_dir = utf8_cargo_dir.join(vendored_dir.file_name().unwrap());
+            copy_crate_dir(&vendored_dir, &src_dir)?;
+            if let Some(checksum) = read_vendored_package_checksum(&vendored_dir)? {
+              checksums.insert(package_ident(name, info.req()), checksum);
+            }
+            src_dir
+          } else if is_fetched_with_cargo(info) {
+            let (src_dir, source) =
+              self.fetch_crate_src_with_cargo(utf8_cargo_dir, name, info, &mut checksums)?;
+            binary_dep_sources.insert(name.clone(), source);
+            src_dir
+          } else {
+            let version = info.req();
+            checksums.insert(
+              package_ident(name, version),
+              self.fetch_crate_checksum(name, version)?,
+            );
+            self.fetch_crate_src(utf8_cargo_dir, name, version)?
+          };
           if let Some(dirname) = src_dir.file_name() {
             src_dirnames.push(dirname.to_string());
           }
@@ -502,6 +1136,28 @@ impl RazeMetadataFetcher {
       .metadata_fetcher
       .fetch_metadata(utf8_cargo_dir, /*include_deps=*/ true)?;
 
+    // Crates are vendored from the temp workspace once `cargo metadata` has added any new binary
+    // dependencies to its lockfile, so they match the lockfile used for planning
+    let run_cargo_vendor = matches!(&self.settings, Some(settings) if settings.run_cargo_vendor);
+    if let (true, true, Some(vendor_dir)) =
+      (run_cargo_vendor, self.update_vendor_dir, &self.vendor_dir)
+    {
+      self.cargo_vendor(utf8_cargo_dir, vendor_dir, /*locked=*/ true)?;
+      verify_vendored_checksums(
+        &Lockfile::load(utf8_cargo_dir.join("Cargo.lock"))?,
+        vendor_dir,
+      )?;
+    }
+
+    // Binary dependencies must be vendored before the temp workspace containing them is removed
+    if let (Some(binary_dep_info), true, Some(vendor_dir)) =
+      (binary_dep_info, self.update_vendor_dir, &self.vendor_dir)
+    {
+      if !binary_dep_info.is_empty() {
+        self.vendor_binary_deps(utf8_cargo_dir, &metadata, binary_dep_info, vendor_dir)?;
+      }
+    }
+
     // In this function because it's metadata, even though it's not returned by `cargo-metadata`
     let platform_features = match self.settings.as_ref() {
       Some(settings) => get_per_platform_features(cargo_dir.path(), settings, &metadata.packages)?,
@@ -514,6 +1170,7 @@ impl RazeMetadataFetcher {
       cargo_workspace_root,
       lockfile: output_lockfile,
       features: platform_features,
+      binary_dep_sources,
     })
   }
 }
@@ -545,9 +1202,9 @@ pub mod tests {
   use tera::Tera;
 
   use super::*;
-  use crate::testing::*;
+  use crate::{settings::tests::dummy_raze_settings, testing::*};
 
-  use std::{fs::File, io::Write, str::FromStr};
+  use std::{cell::RefCell, fs::File, io::Write, rc::Rc, str::FromStr};
 
   pub struct DummyCargoMetadataFetcher {
     pub metadata_template: Option<String>,
@@ -625,6 +1282,14 @@ pub mod tests {
           .with_context(|| format!("Failed to load dummy lockfile:\n{}", basic_lock_contents())),
       }
     }
+
+    fn update_lockfile(
+      &self,
+      crate_root_dir: &Utf8Path,
+      _updates: &[CrateUpdate],
+    ) -> Result<Lockfile> {
+      self.generate_lockfile(crate_root_dir)
+    }
   }
 
   pub fn dummy_raze_metadata_fetcher() -> (RazeMetadataFetcher, MockServer, TempDir) {
@@ -743,6 +1408,345 @@ pub mod tests {
     assert!(path.join("test").exists());
   }
 
+  #[test]
+  fn test_offline_fetch_lists_binary_dependency_downloads() {
+    let (mut fetcher, mock_server, _index_dir) = dummy_raze_metadata_fetcher();
+    fetcher.offline = true;
+
+    let binary_deps: HashMap<String, cargo_toml::Dependency> = vec![(
+      "some-binary".to_owned(),
+      cargo_toml::Dependency::Simple("1.2.3".to_owned()),
+    )]
+    .into_iter()
+    .collect();
+
+    let dir = make_basic_workspace();
+    let err = fetcher
+      .fetch_metadata(utf8_path(dir.as_ref()), Some(&binary_deps), None)
+      .unwrap_err();
+
+    // The index is a local directory, so only the sources need to be downloaded
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "Raze is running in offline mode, but the following need network access:\n  Downloading \
+         the sources of binary dependency `some-binary-1.2.3` from {}/",
+        mock_server.base_url()
+      )
+    );
+  }
+
+  #[test]
+  fn test_fetch_crate_checksum_from_sparse_index() {
+    let (mut fetcher, mock_server, _index_dir) = dummy_raze_metadata_fetcher();
+    fetcher.index_url = Url::parse(&format!("sparse+{}", mock_server.base_url())).unwrap();
+    mock_sparse_index(
+      &mock_server,
+      &format!("{}/api/v1/crates", mock_server.base_url()),
+      "fake-crate",
+      &["3.3.3"],
+    );
+
+    assert_eq!(
+      fetcher.fetch_crate_checksum("fake-crate", "3.3.3").unwrap(),
+      "3330000000000000000000000000000000000000000000000000000000000000"
+    );
+    assert!(!fetcher.index_is_local());
+  }
+
+  #[test]
+  fn test_find_path_to_crate_root_in_git_checkout() {
+    let checkout = TempDir::new().unwrap();
+    let checkout_path = utf8_path(checkout.as_ref());
+    fs::create_dir_all(checkout_path.join(".git")).unwrap();
+    fs::create_dir_all(checkout_path.join("tools/some-binary")).unwrap();
+    let vendor_stderr = |crate_dir: &Utf8Path| {
+      format!(
+        "   Vendoring other v1.2.3 (file:///other#1234abcd) (/other/checkout) to vendor/other-1.2.3\n   \
+         Vendoring some-binary v1.2.3 (file:///repo?rev=1234#1234abcd) ({}) to \
+         vendor/some-binary-1.2.3\nTo use vendored sources, add this to your .cargo/config.toml",
+        crate_dir
+      )
+    };
+
+    assert_eq!(
+      find_path_to_crate_root_in_git_checkout(
+        &vendor_stderr(&checkout_path.join("tools/some-binary")),
+        "some-binary",
+        "1.2.3"
+      )
+      .unwrap()
+      .as_deref(),
+      Some("tools/some-binary")
+    );
+    assert_eq!(
+      find_path_to_crate_root_in_git_checkout(
+        &vendor_stderr(checkout_path),
+        "some-binary",
+        "1.2.3"
+      )
+      .unwrap(),
+      None
+    );
+    assert!(find_path_to_crate_root_in_git_checkout(
+      &vendor_stderr(checkout_path),
+      "some-binary",
+      "1.2.4"
+    )
+    .is_err());
+  }
+
+  /// Writes a script which runs cargo with `cargo_home` as its `CARGO_HOME`, so tests fetching
+  /// from git don't leave checkouts behind in the user's cargo home.
+  #[cfg(target_family = "unix")]
+  fn cargo_with_cargo_home(dir: &Utf8Path, cargo_home: &Utf8Path) -> Utf8PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let script = dir.join("cargo");
+    fs::write(
+      &script,
+      format!(
+        "#!/bin/sh\nCARGO_HOME='{}' exec '{}' \"$@\"\n",
+        cargo_home,
+        cargo_bin_path()
+      ),
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    script
+  }
+
+  #[test]
+  #[cfg(target_family = "unix")]
+  fn test_fetch_binary_dependency_from_git() {
+    let (repo, commit) = mock_git_crate("some-binary", "1.2.3", "tools/some-binary");
+    let binary_deps: HashMap<String, cargo_toml::Dependency> = vec![(
+      "some-binary".to_owned(),
+      cargo_toml::Dependency::Detailed(cargo_toml::DependencyDetail {
+        git: Some(format!("file://{}", repo.as_ref().display())),
+        rev: Some(commit.clone()),
+        ..cargo_toml::DependencyDetail::default()
+      }),
+    )]
+    .into_iter()
+    .collect();
+
+    let cargo_dir = TempDir::new().unwrap();
+    let cargo_home = utf8_path(cargo_dir.as_ref()).join("cargo-home");
+    let mut fetcher = RazeMetadataFetcher::new(
+      cargo_with_cargo_home(utf8_path(cargo_dir.as_ref()), &cargo_home),
+      Url::parse(DEFAULT_CRATE_REGISTRY_URL).unwrap(),
+      Url::parse(DEFAULT_CRATE_INDEX_URL).unwrap(),
+      None,
+    );
+    fetcher.set_lockfile_generator(Box::new(DummyLockfileGenerator {
+      lockfile_contents: None,
+    }));
+    let dir = make_basic_workspace();
+    let raze_metadata = fetcher
+      .fetch_metadata(utf8_path(dir.as_ref()), Some(&binary_deps), None)
+      .unwrap();
+    assert!(cargo_home.join("git/checkouts").is_dir());
+
+    // The binary dependency is added to the workspace, like those from the configured registry
+    let metadata = &raze_metadata.metadata;
+    assert!(metadata
+      .packages
+      .iter()
+      .any(|pkg| pkg.name == "some-binary" && metadata.workspace_members.contains(&pkg.id)));
+
+    let binary_dep_source = &raze_metadata.binary_dep_sources["some-binary"];
+    assert!(binary_dep_source.source.repr.starts_with("git+file://"));
+    assert!(binary_dep_source
+      .source
+      .repr
+      .ends_with(&format!("#{}", commit)));
+    assert_eq!(
+      binary_dep_source.path_to_crate_root.as_deref(),
+      Some("tools/some-binary")
+    );
+    assert!(raze_metadata.checksum_for("some-binary", "1.2.3").is_none());
+  }
+
+  #[test]
+  #[cfg(target_family = "unix")]
+  fn test_binary_dependencies_are_vendored() {
+    let (repo, commit) = mock_git_crate("some-binary", "1.2.3", "tools/some-binary");
+    let binary_deps: HashMap<String, cargo_toml::Dependency> = vec![(
+      "some-binary".to_owned(),
+      cargo_toml::Dependency::Detailed(cargo_toml::DependencyDetail {
+        git: Some(format!("file://{}", repo.as_ref().display())),
+        rev: Some(commit),
+        ..cargo_toml::DependencyDetail::default()
+      }),
+    )]
+    .into_iter()
+    .collect();
+
+    let cargo_dir = TempDir::new().unwrap();
+    let cargo_home = utf8_path(cargo_dir.as_ref()).join("cargo-home");
+    let mut fetcher = RazeMetadataFetcher::new(
+      cargo_with_cargo_home(utf8_path(cargo_dir.as_ref()), &cargo_home),
+      Url::parse(DEFAULT_CRATE_REGISTRY_URL).unwrap(),
+      Url::parse(DEFAULT_CRATE_INDEX_URL).unwrap(),
+      None,
+    );
+    fetcher.set_lockfile_generator(Box::new(DummyLockfileGenerator {
+      lockfile_contents: None,
+    }));
+    let vendor_dir = TempDir::new().unwrap();
+    let vendor_path = utf8_path(vendor_dir.as_ref());
+    fetcher.set_vendor_dir(vendor_path.to_path_buf(), /*update=*/ true);
+
+    let dir = make_basic_workspace();
+    fetcher
+      .fetch_metadata(utf8_path(dir.as_ref()), Some(&binary_deps), None)
+      .unwrap();
+
+    // The crate is vendored by `cargo vendor`, so its checksum file lists each of its files
+    let vendored_crate = vendor_path.join("some-binary-1.2.3");
+    let checksum_file: serde_json::Value = serde_json::from_str(
+      &fs::read_to_string(vendored_crate.join(".cargo-checksum.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(checksum_file["files"]["src/main.rs"].is_string());
+    assert!(checksum_file["package"].is_null());
+
+    // Workspace members which are not binary dependencies are never vendored
+    assert_eq!(fs::read_dir(vendor_path).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn test_vendored_binary_dependencies_are_not_fetched() {
+    let (mut fetcher, _mock_server, _index_dir) = dummy_raze_metadata_fetcher();
+    // Nothing may be fetched from the network, and the mock registry serves no crates
+    fetcher.offline = true;
+    let vendor_dir = TempDir::new().unwrap();
+    let vendor_path = utf8_path(vendor_dir.as_ref());
+    fetcher.set_vendor_dir(vendor_path.to_path_buf(), /*update=*/ false);
+
+    let vendored_crate = vendor_path.join("some-binary-crate-3.3.3");
+    fs::create_dir_all(vendored_crate.join("src")).unwrap();
+    fs::write(
+      vendored_crate.join("Cargo.toml"),
+      "[package]\nname = \"some-binary-crate\"\nversion = \"3.3.3\"\n",
+    )
+    .unwrap();
+    fs::write(vendored_crate.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(
+      vendored_crate.join(".cargo-checksum.json"),
+      serde_json::json!({ "files": {}, "package": "3330000000000000000000000000000000000000000000000000000000000000" })
+        .to_string(),
+    )
+    .unwrap();
+
+    let binary_deps: HashMap<String, cargo_toml::Dependency> = vec![(
+      "some-binary-crate".to_owned(),
+      cargo_toml::Dependency::Simple("3.3.3".to_owned()),
+    )]
+    .into_iter()
+    .collect();
+    let dir = make_basic_workspace();
+    let raze_metadata = fetcher
+      .fetch_metadata(utf8_path(dir.as_ref()), Some(&binary_deps), None)
+      .unwrap();
+
+    assert!(raze_metadata
+      .metadata
+      .packages
+      .iter()
+      .any(|pkg| pkg.name == "some-binary-crate"));
+    assert_eq!(
+      raze_metadata
+        .checksum_for("some-binary-crate", "3.3.3")
+        .map(String::as_str),
+      Some("3330000000000000000000000000000000000000000000000000000000000000")
+    );
+
+    // Binary dependencies which are not vendored yet must be fetched
+    let binary_deps: HashMap<String, cargo_toml::Dependency> = vec![(
+      "some-binary-crate".to_owned(),
+      cargo_toml::Dependency::Simple("3.3.4".to_owned()),
+    )]
+    .into_iter()
+    .collect();
+    let err = fetcher
+      .fetch_metadata(utf8_path(dir.as_ref()), Some(&binary_deps), None)
+      .unwrap_err();
+    assert!(err.to_string().contains("some-binary-crate-3.3.4"));
+  }
+
+  #[test]
+  fn test_verify_vendored_checksums() {
+    let lockfile = Lockfile::from_str(advanced_lock_contents()).unwrap();
+    let vendor_dir = TempDir::new().unwrap();
+    let vendor_path = utf8_path(vendor_dir.as_ref());
+    let vendor_crate = |ident: &str, package_checksum: &str| {
+      let contents = "pub fn f() {}";
+      fs::create_dir_all(vendor_path.join(ident).join("src")).unwrap();
+      fs::write(vendor_path.join(ident).join("src/lib.rs"), contents).unwrap();
+      fs::write(
+        vendor_path.join(ident).join(".cargo-checksum.json"),
+        serde_json::json!({
+          "files": { "src/lib.rs": Sha256::digest(contents.as_bytes()).to_hex() },
+          "package": package_checksum,
+        })
+        .to_string(),
+      )
+      .unwrap();
+    };
+
+    // Crates must be vendored into versioned directories
+    assert!(verify_vendored_checksums(&lockfile, vendor_path).is_err());
+
+    let proc_macro2_checksum = "1e0704ee1a7e00d7bb417d0770ea303c1bccbabf0ef1667dae92b5967f5f8a71";
+    let unicode_xid_checksum = "f7fe0bb3479651439c9112f72b6c505038574c9fbb575ed1bf3b797fa39dd564";
+    vendor_crate("proc-macro2-1.0.24", proc_macro2_checksum);
+    vendor_crate("unicode-xid-0.2.1", unicode_xid_checksum);
+    assert!(verify_vendored_checksums(&lockfile, vendor_path).is_ok());
+
+    // Crates vendored from a different release than the lockfile's are detected
+    vendor_crate("unicode-xid-0.2.1", "0000");
+    let err = verify_vendored_checksums(&lockfile, vendor_path).unwrap_err();
+    assert!(err.to_string().contains("do not match the lockfile"));
+    assert!(err.to_string().contains("\"unicode-xid-0.2.1\""));
+    assert!(!err.to_string().contains("proc-macro2"));
+    vendor_crate("unicode-xid-0.2.1", unicode_xid_checksum);
+
+    // Edited sources are detected
+    fs::write(vendor_path.join("unicode-xid-0.2.1/src/lib.rs"), "").unwrap();
+    let err = verify_vendored_checksums(&lockfile, vendor_path).unwrap_err();
+    assert!(err
+      .to_string()
+      .contains("do not match their `.cargo-checksum.json`"));
+    assert!(err.to_string().contains("unicode-xid-0.2.1/src/lib.rs"));
+    assert!(!err.to_string().contains("proc-macro2"));
+
+    // As are removed ones
+    vendor_crate("unicode-xid-0.2.1", unicode_xid_checksum);
+    fs::remove_file(vendor_path.join("proc-macro2-1.0.24/src/lib.rs")).unwrap();
+    let err = verify_vendored_checksums(&lockfile, vendor_path).unwrap_err();
+    assert!(err.to_string().contains("proc-macro2-1.0.24/src/lib.rs"));
+    assert!(!err.to_string().contains("unicode-xid"));
+  }
+
+  #[test]
+  fn test_cargo_vendor_offline() {
+    let (mut fetcher, _mock_server, _index_url) = dummy_raze_metadata_fetcher();
+    fetcher.offline = true;
+    let vendor_dir = TempDir::new().unwrap();
+    let vendor_path = utf8_path(vendor_dir.as_ref());
+
+    // A workspace without dependencies can be vendored without touching the network
+    let dir = make_basic_workspace();
+    let cargo_dir = utf8_path(dir.as_ref());
+    fetcher
+      .cargo_vendor(cargo_dir, vendor_path, /*locked=*/ true)
+      .unwrap();
+    let lockfile = Lockfile::load(cargo_dir.join("Cargo.lock")).unwrap();
+    assert!(verify_vendored_checksums(&lockfile, vendor_path).is_ok());
+  }
+
   #[test]
   fn test_inject_dependency_to_workspace() {
     let (fetcher, _mock_server, _index_url) = dummy_raze_metadata_fetcher();
@@ -860,4 +1864,183 @@ pub mod tests {
         .unwrap()
     );
   }
+
+  #[test]
+  fn test_parse_crate_update() {
+    assert_eq!(
+      CrateUpdate::from_str("openssl").unwrap(),
+      CrateUpdate {
+        name: "openssl".to_owned(),
+        precise: None,
+      }
+    );
+    assert_eq!(
+      CrateUpdate::from_str("openssl@0.10.55").unwrap(),
+      CrateUpdate {
+        name: "openssl".to_owned(),
+        precise: Some(Version::new(0, 10, 55)),
+      }
+    );
+    assert!(CrateUpdate::from_str("openssl@latest").is_err());
+  }
+
+  #[test]
+  fn test_cargo_generate_lockfile_updates_reused_lockfile() {
+    let (mut fetcher, _mock_server, _index_url) = dummy_raze_metadata_fetcher();
+    fetcher.set_lockfile_generator(Box::new(DummyLockfileGenerator {
+      lockfile_contents: Some(advanced_lock_contents().to_string()),
+    }));
+    fetcher.set_crate_updates(vec![CrateUpdate::from_str("unicode-xid").unwrap()]);
+
+    let crate_dir = make_workspace(advanced_toml_contents(), None);
+    let reused_lockfile =
+      Utf8PathBuf::from_path_buf(crate_dir.as_ref().join("locks_test/Cargo.raze.lock")).unwrap();
+    fs::create_dir_all(reused_lockfile.parent().unwrap()).unwrap();
+    fs::write(&reused_lockfile, advanced_lock_contents()).unwrap();
+
+    // Updating crates in a reused lockfile produces a new lockfile
+    assert_eq!(
+      fetcher
+        .cargo_generate_lockfile(&Some(reused_lockfile), utf8_path(crate_dir.as_ref()))
+        .unwrap(),
+      Some(Lockfile::from_str(advanced_lock_contents()).unwrap())
+    );
+  }
+
+  #[test]
+  fn test_cargo_lockfile_generator_updates_crates() {
+    let crate_dir = make_workspace(basic_toml_contents(), Some(basic_lock_contents()));
+    let generator = CargoLockfileGenerator {
+      cargo_bin_path: cargo_bin_path(),
+      offline: true,
+      version_resolution: VersionResolution::Maximal,
+    };
+
+    let lockfile = generator
+      .update_lockfile(
+        utf8_path(crate_dir.as_ref()),
+        &[CrateUpdate::from_str("test").unwrap()],
+      )
+      .unwrap();
+    assert_eq!(lockfile.packages[0].name.as_str(), "test");
+
+    let err = generator
+      .update_lockfile(
+        utf8_path(crate_dir.as_ref()),
+        &[CrateUpdate::from_str("not-a-dependency").unwrap()],
+      )
+      .unwrap_err();
+    assert!(err
+      .to_string()
+      .starts_with("Failed to update `not-a-dependency`"));
+  }
+
+  #[test]
+  #[cfg(target_family = "unix")]
+  fn test_cargo_lockfile_generator_requires_unstable_cargo_for_minimal_versions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Unstable flags are accepted by any cargo when bootstrapping
+    if matches!(std::env::var("RUSTC_BOOTSTRAP").as_deref(), Ok("1")) {
+      return;
+    }
+
+    let crate_dir = make_workspace(basic_toml_contents(), None);
+    let stable_cargo = utf8_path(crate_dir.as_ref()).join("stable-cargo");
+    fs::write(
+      &stable_cargo,
+      "#!/bin/sh\necho 'cargo 1.62.0 (a748cf5a3 2022-06-08)'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&stable_cargo, fs::Permissions::from_mode(0o755)).unwrap();
+
+    for (version_resolution, flag) in [
+      (VersionResolution::Minimal, "-Z minimal-versions"),
+      (
+        VersionResolution::DirectMinimal,
+        "-Z direct-minimal-versions",
+      ),
+    ] {
+      let generator = CargoLockfileGenerator {
+        cargo_bin_path: stable_cargo.clone(),
+        offline: true,
+        version_resolution,
+      };
+      let err = generator
+        .generate_lockfile(utf8_path(crate_dir.as_ref()))
+        .unwrap_err();
+      assert!(err.to_string().contains(flag), "{}", err);
+      assert!(err.to_string().contains("nightly"), "{}", err);
+    }
+  }
+
+  /// Records the crates it is asked to update
+  struct RecordingLockfileGenerator {
+    updates: Rc<RefCell<Vec<CrateUpdate>>>,
+  }
+
+  impl LockfileGenerator for RecordingLockfileGenerator {
+    fn generate_lockfile(&self, _crate_root_dir: &Utf8Path) -> Result<Lockfile> {
+      Ok(Lockfile::from_str(advanced_lock_contents())?)
+    }
+
+    fn update_lockfile(
+      &self,
+      _crate_root_dir: &Utf8Path,
+      updates: &[CrateUpdate],
+    ) -> Result<Lockfile> {
+      self.updates.borrow_mut().extend_from_slice(updates);
+      Ok(Lockfile::from_str(advanced_lock_contents())?)
+    }
+  }
+
+  #[test]
+  fn test_cargo_generate_lockfile_applies_precise_versions() {
+    let (mut fetcher, _mock_server, _index_url) = dummy_raze_metadata_fetcher();
+    let updates = Rc::new(RefCell::new(Vec::new()));
+    fetcher.set_lockfile_generator(Box::new(RecordingLockfileGenerator {
+      updates: updates.clone(),
+    }));
+    let mut settings = dummy_raze_settings();
+    settings
+      .precise_versions
+      .insert("unicode-xid".to_owned(), Version::new(0, 2, 0));
+    fetcher.settings = Some(settings);
+    fetcher.set_crate_updates(vec![CrateUpdate::from_str("proc-macro2").unwrap()]);
+
+    let crate_dir = make_workspace(advanced_toml_contents(), None);
+    assert!(fetcher
+      .cargo_generate_lockfile(&None, utf8_path(crate_dir.as_ref()))
+      .unwrap()
+      .is_some());
+
+    // Pins are applied before the requested updates
+    assert_eq!(
+      *updates.borrow(),
+      vec![
+        CrateUpdate {
+          name: "unicode-xid".to_owned(),
+          precise: Some(Version::new(0, 2, 0)),
+        },
+        CrateUpdate {
+          name: "proc-macro2".to_owned(),
+          precise: None,
+        },
+      ]
+    );
+
+    // Reused lockfiles are left as they are, except for requested updates
+    updates.borrow_mut().clear();
+    let reused_lockfile =
+      Utf8PathBuf::from_path_buf(crate_dir.as_ref().join("locks_test/Cargo.raze.lock")).unwrap();
+    fs::create_dir_all(reused_lockfile.parent().unwrap()).unwrap();
+    fs::write(&reused_lockfile, advanced_lock_contents()).unwrap();
+    fetcher
+      .cargo_generate_lockfile(&Some(reused_lockfile), utf8_path(crate_dir.as_ref()))
+      .unwrap();
+    assert_eq!(
+      *updates.borrow(),
+      vec![CrateUpdate::from_str("proc-macro2").unwrap()]
+    );
+  }
 }
@@ -12,8 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod crate_catalog;
-mod license;
+pub(crate) mod crate_catalog;
+pub(crate) mod license;
 mod subplanners;
 
 use anyhow::Result;
@@ -84,15 +84,17 @@ pub mod tests {
   use std::{collections::BTreeMap, collections::HashMap, collections::HashSet};
 
   use crate::{
-    metadata::tests::{
-      dummy_raze_metadata, dummy_raze_metadata_fetcher, DummyCargoMetadataFetcher,
+    context::GitRepo,
+    metadata::{
+      tests::{dummy_raze_metadata, dummy_raze_metadata_fetcher, DummyCargoMetadataFetcher},
+      BinaryDependencySource,
     },
-    settings::{tests::*, GenMode},
+    settings::{tests::*, CrateSettings, GenMode, TargetedRustcSettings},
     testing::*,
   };
 
   use super::*;
-  use camino::Utf8PathBuf;
+  use camino::{Utf8Path, Utf8PathBuf};
   use cargo_metadata::PackageId;
   use indoc::indoc;
   use itertools::Itertools;
@@ -109,6 +111,7 @@ pub mod tests {
       lockfile: None,
       checksums: HashMap::new(),
       features: BTreeMap::new(),
+      binary_dep_sources: HashMap::new(),
     }
   }
 
@@ -161,6 +164,98 @@ pub mod tests {
     );
   }
 
+  #[test]
+  fn test_plan_build_resolves_targeted_rustc_settings() {
+    let mut crate_settings = CrateSettings::default();
+    crate_settings.targeted_rustc_settings.insert(
+      "cfg(unix)".to_owned(),
+      TargetedRustcSettings {
+        additional_flags: vec!["--cfg=unix_only".to_owned()],
+        additional_env: vec![("PLATFORM".to_owned(), "unix".to_owned())]
+          .into_iter()
+          .collect(),
+      },
+    );
+    crate_settings.targeted_rustc_settings.insert(
+      "x86_64-apple-darwin".to_owned(),
+      TargetedRustcSettings {
+        additional_flags: vec!["--cfg=darwin_only".to_owned()],
+        additional_env: vec![("PLATFORM".to_owned(), "darwin".to_owned())]
+          .into_iter()
+          .collect(),
+      },
+    );
+
+    let mut settings = dummy_raze_settings();
+    settings.targets = Some(
+      vec![
+        "x86_64-apple-darwin".to_owned(),
+        "x86_64-pc-windows-msvc".to_owned(),
+        "x86_64-unknown-linux-gnu".to_owned(),
+      ]
+      .into_iter()
+      .collect(),
+    );
+    settings.crates.insert(
+      "test_dep".to_owned(),
+      vec![(VersionReq::STAR, crate_settings)]
+        .into_iter()
+        .collect(),
+    );
+
+    let planned_build = BuildPlannerImpl::new(
+      template_raze_metadata(templates::DUMMY_MODIFIED_METADATA),
+      settings,
+    )
+    .plan_build(None)
+    .unwrap();
+
+    let dep = &planned_build.crate_contexts[0];
+    let platforms = dep
+      .platform_rustc_settings
+      .keys()
+      .map(String::as_str)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      platforms,
+      vec!["x86_64-apple-darwin", "x86_64-unknown-linux-gnu"]
+    );
+
+    let darwin = &dep.platform_rustc_settings["x86_64-apple-darwin"];
+    assert_eq!(
+      darwin.additional_flags,
+      vec!["--cfg=unix_only", "--cfg=darwin_only"]
+    );
+    assert_eq!(darwin.additional_env["PLATFORM"], "darwin");
+
+    let linux = &dep.platform_rustc_settings["x86_64-unknown-linux-gnu"];
+    assert_eq!(linux.additional_flags, vec!["--cfg=unix_only"]);
+    assert_eq!(linux.additional_env["PLATFORM"], "unix");
+  }
+
+  #[test]
+  fn test_plan_build_rejects_invalid_targeted_rustc_settings() {
+    let mut crate_settings = CrateSettings::default();
+    crate_settings
+      .targeted_rustc_settings
+      .insert("cfg(unix".to_owned(), TargetedRustcSettings::default());
+
+    let mut settings = dummy_raze_settings();
+    settings.crates.insert(
+      "test_dep".to_owned(),
+      vec![(VersionReq::STAR, crate_settings)]
+        .into_iter()
+        .collect(),
+    );
+
+    let planned_build_res = BuildPlannerImpl::new(
+      template_raze_metadata(templates::DUMMY_MODIFIED_METADATA),
+      settings,
+    )
+    .plan_build(None);
+    assert!(planned_build_res.is_err());
+  }
+
   pub fn dummy_workspace_crate_metadata(metadata_template: &str) -> RazeMetadata {
     let dir = make_basic_workspace();
     let (mut fetcher, _server, _index_dir) = dummy_raze_metadata_fetcher();
@@ -199,6 +294,7 @@ pub mod tests {
       lockfile: None,
       checksums: HashMap::new(),
       features: BTreeMap::new(),
+      binary_dep_sources: HashMap::new(),
     }
   }
 
@@ -412,17 +508,13 @@ pub mod tests {
     assert_eq!(flate2.targeted_deps[0].deps.dependencies.len(), 0);
   }
 
-  fn dummy_binary_dependency_metadata(is_remote_genmode: bool) -> (RazeMetadata, RazeSettings) {
+  fn dummy_binary_dependency_metadata() -> (RazeMetadata, RazeSettings) {
     let (mut fetcher, server, index_dir) = dummy_raze_metadata_fetcher();
 
-    // Only in cases where `RazeSettings::genmode == "Remote"` do we exepct the metadata to be anything different
-    // than the standard metadata. So we use a generated template to represent that state.
+    // Binary dependencies are added to the workspace, so we use a generated template to represent
+    // that state.
     let dummy_metadata_fetcher = DummyCargoMetadataFetcher {
-      metadata_template: if is_remote_genmode {
-        Some(templates::DUMMY_BINARY_DEPENDENCY_REMOTE.to_string())
-      } else {
-        Some(templates::BASIC_METADATA.to_string())
-      },
+      metadata_template: Some(templates::DUMMY_BINARY_DEPENDENCY_REMOTE.to_string()),
     };
     fetcher.set_metadata_fetcher(Box::new(dummy_metadata_fetcher));
 
@@ -453,8 +545,7 @@ pub mod tests {
 
   #[test]
   fn test_binary_dependencies_remote_genmode() {
-    let (raze_metadata, mut settings) =
-      dummy_binary_dependency_metadata(/*is_remote_genmode=*/ true);
+    let (raze_metadata, mut settings) = dummy_binary_dependency_metadata();
     settings.genmode = GenMode::Remote;
 
     // Make sure the dummy settings contain the information we expect
@@ -493,10 +584,75 @@ pub mod tests {
     );
   }
 
+  #[test]
+  fn test_binary_dependencies_from_git_and_alternate_registries() {
+    let (mut raze_metadata, mut settings) = dummy_binary_dependency_metadata();
+    settings.genmode = GenMode::Remote;
+    let plan_binary_dependency = |raze_metadata: RazeMetadata| {
+      BuildPlannerImpl::new(raze_metadata, settings.clone())
+        .plan_build(None)
+        .unwrap()
+        .crate_contexts
+        .into_iter()
+        .find(|ctx| ctx.pkg_name == "some-binary-crate")
+        .unwrap()
+    };
+
+    let commit = "0123456789abcdef0123456789abcdef01234567";
+    raze_metadata.binary_dep_sources.insert(
+      "some-binary-crate".to_owned(),
+      BinaryDependencySource {
+        source: cargo_metadata::Source {
+          repr: format!("git+https://github.com/example/tools?rev=v1#{}", commit),
+        },
+        path_to_crate_root: Some("crates/some-binary-crate".to_owned()),
+      },
+    );
+    let context = plan_binary_dependency(raze_metadata.clone());
+    assert_eq!(context.source_details.download_url, None);
+    assert_eq!(
+      context.source_details.git_data,
+      Some(GitRepo {
+        remote: "https://github.com/example/tools".to_owned(),
+        commit: commit.to_owned(),
+        path_to_crate_root: Some("crates/some-binary-crate".to_owned()),
+      })
+    );
+    assert!(context
+      .targets
+      .iter()
+      .all(|target| target.path.starts_with("crates/some-binary-crate/")));
+
+    let mock_server = httpmock::MockServer::start();
+    mock_sparse_index(
+      &mock_server,
+      &format!("{}/dl/{{crate}}-{{version}}.crate", mock_server.base_url()),
+      "some-binary-crate",
+      &["3.3.3"],
+    );
+    raze_metadata.binary_dep_sources.insert(
+      "some-binary-crate".to_owned(),
+      BinaryDependencySource {
+        source: cargo_metadata::Source {
+          repr: format!("sparse+{}/", mock_server.base_url()),
+        },
+        path_to_crate_root: None,
+      },
+    );
+    let context = plan_binary_dependency(raze_metadata);
+    assert_eq!(context.source_details.git_data, None);
+    assert_eq!(
+      context.source_details.download_url.unwrap().as_str(),
+      format!(
+        "{}/dl/some-binary-crate-3.3.3.crate",
+        mock_server.base_url()
+      )
+    );
+  }
+
   #[test]
   fn test_binary_dependencies_vendored_genmode() {
-    let (raze_metadata, mut settings) =
-      dummy_binary_dependency_metadata(/*is_remote_genmode=*/ false);
+    let (raze_metadata, mut settings) = dummy_binary_dependency_metadata();
     settings.genmode = GenMode::Vendored;
 
     // Make sure the dummy settings contain the information we expect
@@ -515,17 +671,26 @@ pub mod tests {
       )))
       .unwrap();
 
-    // Vendored builds do not use binary dependencies and should not alter the outputs
-    assert!(!planned_build
+    // Vendored binary dependencies are built from the vendor directory
+    let context = planned_build
       .crate_contexts
       .iter()
-      .any(|ctx| ctx.pkg_name == "some-binary-crate"));
+      .find(|ctx| ctx.pkg_name == "some-binary-crate" && ctx.pkg_version == version)
+      .unwrap();
+    assert!(context.is_binary_dependency);
+    assert_eq!(
+      context.expected_build_path,
+      "vendor/some-binary-crate-3.3.3/BUILD"
+    );
+    assert_eq!(
+      context.workspace_path_to_crate,
+      "//cargo/vendor/some-binary-crate-3.3.3"
+    );
   }
 
   #[test]
   fn test_workspace_context_contains_no_binary_dependencies() {
-    let (raze_metadata, mut settings) =
-      dummy_binary_dependency_metadata(/*is_remote_genmode=*/ true);
+    let (raze_metadata, mut settings) = dummy_binary_dependency_metadata();
     settings.genmode = GenMode::Remote;
 
     // Make sure the dummy settings contain the information we expect
@@ -830,6 +995,67 @@ pub mod tests {
       .iter()
       .any(|ctx| ctx.pkg_name == "unicode-xid" && ctx.pkg_version == Version::new(0, 2, 1)));
   }
+
+  #[test]
+  fn test_plan_build_produces_workspace_member_packages() {
+    let planned_build =
+      BuildPlannerImpl::new(dummy_workspace_members_metadata(), dummy_raze_settings())
+        .plan_build(None)
+        .unwrap();
+
+    let members: Vec<(&str, &Utf8Path)> = planned_build
+      .workspace_context
+      .workspace_member_packages
+      .iter()
+      .map(|member| (member.name.as_str(), member.path.as_path()))
+      .collect();
+    assert_eq!(
+      members,
+      vec![
+        ("lib_a", Utf8Path::new("lib_a")),
+        ("lib_b", Utf8Path::new("lib_b"))
+      ]
+    );
+    assert!(planned_build
+      .workspace_context
+      .workspace_member_packages
+      .iter()
+      .all(|member| member.workspace_member_dependencies.is_empty()));
+  }
+
+  #[test]
+  fn test_plan_build_produces_download_urls_from_sparse_registries() {
+    let mock_server = httpmock::MockServer::start();
+    let mut raze_metadata = template_raze_metadata(templates::DUMMY_MODIFIED_METADATA);
+    let package = raze_metadata
+      .metadata
+      .packages
+      .iter_mut()
+      .find(|pkg| pkg.name == "test_dep")
+      .unwrap();
+    package.source = Some(cargo_metadata::Source {
+      repr: format!("sparse+{}/", mock_server.base_url()),
+    });
+    let version = package.version.to_string();
+    let mocks = mock_sparse_index(
+      &mock_server,
+      &format!("{}/dl/{{crate}}-{{version}}.crate", mock_server.base_url()),
+      "test_dep",
+      &[&version],
+    );
+
+    let planned_build = BuildPlannerImpl::new(raze_metadata, dummy_raze_settings())
+      .plan_build(None)
+      .unwrap();
+
+    let dep = &planned_build.crate_contexts[0];
+    assert_eq!(
+      dep.source_details.download_url.as_ref().unwrap().as_str(),
+      format!("{}/dl/test_dep-{}.crate", mock_server.base_url(), version)
+    );
+    mocks[0].assert();
+  }
+
   // TODO(acmcarther): Add tests:
   // TODO(acmcarther): Extra flags work
   // TODO(acmcarther): Extra deps work
@@ -12,16 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::context::LicenseData;
+use crate::{context::LicenseData, settings::LicensePolicy};
 
+use serde::Deserialize;
 use spdx::{
   expression::{ExprNode, Operator},
-  Expression, LicenseItem,
+  lexer::{Lexer, Token},
+  Expression, LicenseItem, Licensee, ParseMode,
 };
 
 // KEEP ORDERED: The order dictates the preference.
 /// The list of Bazel-known license types
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BazelLicenseType {
   Unencumbered,
   Notice,
@@ -133,11 +136,14 @@ pub fn get_license_from_str(cargo_license_str: &str) -> LicenseData {
           cargo_license_str
         ),
         rating: BazelLicenseType::Restricted.to_bazel_rating().into(),
+        expression: cargo_license_str.into(),
+        spdx_ids: Vec::new(),
       };
     }
   };
 
   let mut license_stack: Vec<BazelSpdxLicense> = Vec::new();
+  let mut spdx_ids: Vec<String> = Vec::new();
   // All of the unwraps are safe because we control the contents of the vector
   for node in license_expression.iter() {
     match node {
@@ -156,8 +162,14 @@ pub fn get_license_from_str(cargo_license_str: &str) -> LicenseData {
       ExprNode::Req(requirement) => {
         // Unwrap is safe because there was no parse error so the license type must exist
         match &requirement.req.license {
-          LicenseItem::SPDX { id, .. } => {
+          LicenseItem::SPDX { id, or_later } => {
             let req_name = id.name;
+            // GNU licenses are normalized to their root id when parsed, so restore the suffix
+            spdx_ids.push(match (id.is_gnu(), or_later) {
+              (true, true) => format!("{}-or-later", req_name),
+              (true, false) => format!("{}-only", req_name),
+              (false, _) => req_name.to_owned(),
+            });
             // Push requirement onto stack
             license_stack.push(BazelSpdxLicense {
               name: req_name.into(),
@@ -175,6 +187,9 @@ pub fn get_license_from_str(cargo_license_str: &str) -> LicenseData {
     };
   }
 
+  spdx_ids.sort();
+  spdx_ids.dedup();
+
   let crate_license = license_stack.pop().unwrap();
   LicenseData {
     name: format!(
@@ -182,9 +197,106 @@ pub fn get_license_from_str(cargo_license_str: &str) -> LicenseData {
       crate_license.name, crate_license.expression
     ),
     rating: crate_license.license.to_bazel_rating().into(),
+    // UNWRAP: The expression was parsed successfully, so it can be lexed
+    expression: normalize_license_expression(cargo_license_str).unwrap(),
+    spdx_ids,
+  }
+}
+
+/// Rewrites a license expression accepted by crates.io in standard SPDX syntax, such as
+/// `MIT OR Apache-2.0` for `MIT/Apache-2.0`. Unlike the expression built up while rating a
+/// license, ids keep their `+` or `-or-later` suffixes and `LicenseRef-` prefixes.
+fn normalize_license_expression(cargo_license_str: &str) -> Option<String> {
+  let mut expression = String::new();
+  for lexer_token in Lexer::new_mode(cargo_license_str, ParseMode::Lax) {
+    let term = match lexer_token.ok()?.token {
+      Token::SPDX(id) => id.name.to_owned(),
+      Token::LicenseRef {
+        doc_ref: Some(doc_ref),
+        lic_ref,
+      } => format!("DocumentRef-{}:LicenseRef-{}", doc_ref, lic_ref),
+      Token::LicenseRef {
+        doc_ref: None,
+        lic_ref,
+      } => format!("LicenseRef-{}", lic_ref),
+      Token::Exception(id) => id.name.to_owned(),
+      Token::Plus => "+".to_owned(),
+      Token::OpenParen => "(".to_owned(),
+      Token::CloseParen => ")".to_owned(),
+      Token::With => "WITH".to_owned(),
+      Token::And => "AND".to_owned(),
+      Token::Or => "OR".to_owned(),
+    };
+
+    let is_attached = matches!(term.as_str(), "+" | ")") || expression.ends_with('(');
+    if !expression.is_empty() && !is_attached {
+      expression.push(' ');
+    }
+    expression.push_str(&term);
+  }
+
+  Some(expression)
+}
+
+/// Checks a cargo license string against a license policy, returning a description of why the
+/// license violates the policy if it does.
+pub fn find_license_policy_violation(
+  cargo_license_str: &str,
+  policy: &LicensePolicy,
+) -> Option<String> {
+  if cargo_license_str.is_empty() {
+    return Some("No license expression is declared".to_owned());
+  }
+
+  let license_expression = match Expression::parse_mode(cargo_license_str, spdx::ParseMode::Lax) {
+    Ok(expression) => expression,
+    Err(_) => return Some("Failed to parse as an SPDX license string".to_owned()),
+  };
+
+  let allowed = parse_licensees(&policy.allowed);
+  let denied = parse_licensees(&policy.denied);
+  let result = license_expression.evaluate_with_failures(|requirement| {
+    let license = match &requirement.license {
+      LicenseItem::SPDX { id, .. } => get_bazel_license_type(id.name),
+      LicenseItem::Other { .. } => BazelLicenseType::Restricted,
+    };
+    let is_allowed = allowed.is_empty()
+      || allowed
+        .iter()
+        .any(|licensee| licensee.satisfies(requirement));
+    let is_denied = denied
+      .iter()
+      .any(|licensee| licensee.satisfies(requirement));
+    let is_within_rating = policy
+      .max_rating
+      .as_ref()
+      .map(|max_rating| license <= *max_rating)
+      .unwrap_or(true);
+    is_allowed && !is_denied && is_within_rating
+  });
+
+  match result {
+    Ok(()) => None,
+    Err(failures) => Some(format!(
+      "Not permitted to use {}",
+      failures
+        .iter()
+        .map(|failure| &license_expression.as_ref()
+          [failure.span.start as usize..failure.span.end as usize])
+        .collect::<Vec<&str>>()
+        .join(", ")
+    )),
   }
 }
 
+/// Parses the SPDX license ids of a license policy. Invalid ids are rejected when loading settings.
+fn parse_licensees(license_ids: &[String]) -> Vec<Licensee> {
+  license_ids
+    .iter()
+    .filter_map(|license_id| Licensee::parse(license_id).ok())
+    .collect()
+}
+
 fn get_bazel_license_type(license_str: &str) -> BazelLicenseType {
   match license_str {
     "AFL-2.1" => BazelLicenseType::Notice,
@@ -631,4 +743,103 @@ mod tests {
     assert_eq!(license.name, "no license");
     assert_eq!(license.rating, "restricted");
   }
+
+  #[test]
+  fn spdx_ids_are_collected_from_the_expression() {
+    let license = get_license_from_str("MIT/Apache-2.0 AND (MIT OR GPL-3.0-only OR LGPL-2.1+)");
+    assert_eq!(
+      license.spdx_ids,
+      vec!["Apache-2.0", "GPL-3.0-only", "LGPL-2.1-or-later", "MIT"]
+    );
+
+    assert!(get_license_from_str("MIT5.0").spdx_ids.is_empty());
+  }
+
+  #[test]
+  fn license_expressions_are_normalized_spdx() {
+    for (cargo_license_str, expression) in [
+      ("MIT/Apache-2.0", "MIT OR Apache-2.0"),
+      ("MIT / Apache-2.0", "MIT OR Apache-2.0"),
+      ("GPL-3.0-or-later", "GPL-3.0-or-later"),
+      ("LGPL-2.1+ and MIT", "LGPL-2.1+ AND MIT"),
+      (
+        "(MIT OR Apache-2.0) AND LicenseRef-ring",
+        "(MIT OR Apache-2.0) AND LicenseRef-ring",
+      ),
+      (
+        "Apache-2.0 WITH LLVM-exception",
+        "Apache-2.0 WITH LLVM-exception",
+      ),
+    ] {
+      assert_eq!(
+        get_license_from_str(cargo_license_str).expression,
+        expression,
+        "{}",
+        cargo_license_str
+      );
+    }
+  }
+
+  #[test]
+  fn license_policy_allows_any_satisfying_alternative() {
+    let policy = LicensePolicy {
+      denied: vec!["GPL-3.0".to_owned()],
+      ..LicensePolicy::default()
+    };
+    assert_eq!(
+      find_license_policy_violation("MIT OR GPL-3.0", &policy),
+      None
+    );
+    assert_eq!(
+      find_license_policy_violation("MIT AND GPL-3.0", &policy),
+      Some("Not permitted to use GPL-3.0".to_owned())
+    );
+
+    // GNU licenses are matched regardless of the suffix of their id
+    assert_eq!(
+      find_license_policy_violation("MIT AND GPL-3.0-only", &policy),
+      Some("Not permitted to use GPL-3.0-only".to_owned())
+    );
+  }
+
+  #[test]
+  fn license_policy_restricts_to_allowed_licenses() {
+    let policy = LicensePolicy {
+      allowed: vec!["MIT".to_owned(), "Apache-2.0".to_owned()],
+      ..LicensePolicy::default()
+    };
+    assert_eq!(
+      find_license_policy_violation("MIT/Apache-2.0", &policy),
+      None
+    );
+    assert_eq!(
+      find_license_policy_violation("Apache-2.0 AND ISC", &policy),
+      Some("Not permitted to use ISC".to_owned())
+    );
+    assert_eq!(
+      find_license_policy_violation("", &policy),
+      Some("No license expression is declared".to_owned())
+    );
+  }
+
+  #[test]
+  fn license_policy_enforces_max_rating() {
+    let policy = LicensePolicy {
+      max_rating: Some(BazelLicenseType::Reciprocal),
+      ..LicensePolicy::default()
+    };
+    assert_eq!(find_license_policy_violation("MPL-2.0", &policy), None);
+    assert_eq!(
+      find_license_policy_violation("MIT OR AGPL-3.0", &policy),
+      None
+    );
+    assert_eq!(
+      find_license_policy_violation("AGPL-3.0", &policy),
+      Some("Not permitted to use AGPL-3.0".to_owned())
+    );
+    assert_eq!(
+      find_license_policy_violation("MIT5.0", &policy),
+      Some("Failed to parse as an SPDX license string".to_owned())
+    );
+  }
 }
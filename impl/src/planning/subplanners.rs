@@ -29,13 +29,14 @@ use crate::{
   context::{
     BuildableDependency, BuildableTarget, CrateContext, CrateDependencyContext,
     CrateTargetedDepContext, DependencyAlias, GitRepo, LicenseData, SourceDetails,
-    WorkspaceContext,
+    WorkspaceContext, WorkspaceMemberPackage,
   },
   error::{RazeError, PLEASE_FILE_A_BUG},
   features::Features,
-  metadata::RazeMetadata,
+  metadata::{BinaryDependencySource, RazeMetadata},
   planning::license,
-  settings::{CrateSettings, GenMode, RazeSettings},
+  registry::{self, RegistryIndex},
+  settings::{CrateSettings, GenMode, RazeSettings, TargetedRustcSettings},
   util,
 };
 
@@ -43,7 +44,6 @@ use super::{
   crate_catalog::{CrateCatalog, CrateCatalogEntry},
   PlannedBuild,
 };
-use cargo_toml::Dependency;
 use url::Url;
 
 /// Named type to reduce declaration noise for deducing the crate contexts
@@ -65,6 +65,7 @@ struct CrateSubplanner<'planner> {
   crate_settings: Option<&'planner CrateSettings>,
   sha256: &'planner Option<String>,
   features: &'planner Option<&'planner Features>,
+  binary_dep_source: Option<&'planner BinaryDependencySource>,
 }
 
 /// An internal working planner for generating context for a whole workspace.
@@ -91,31 +92,61 @@ impl<'planner> WorkspaceSubplanner<'planner> {
 
   /// Constructs a workspace context from settings.
   fn produce_workspace_context(&self) -> WorkspaceContext {
-    // Gather the workspace member paths for all workspace members
-    let workspace_members = self
-      .metadata
-      .metadata
+    let metadata = &self.metadata.metadata;
+    let workspace_member_path = |pkg: &Package| {
+      util::get_workspace_member_path(pkg.manifest_path.as_ref(), metadata.workspace_root.as_ref())
+    };
+
+    // Gather all workspace members, excluding binary dependencies
+    let member_packages: Vec<(&Package, Utf8PathBuf)> = metadata
       .workspace_members
       .iter()
-      .filter_map(|pkg_id| {
-        let workspace_member = self
-          .metadata
-          .metadata
-          .packages
-          .iter()
-          .find(|pkg| pkg.id == *pkg_id);
-        if let Some(pkg) = workspace_member {
-          // Don't include binary dependencies
-          if self.settings.binary_deps.contains_key(&pkg.name) {
-            None
-          } else {
-            util::get_workspace_member_path(
-              pkg.manifest_path.as_ref(),
-              self.metadata.metadata.workspace_root.as_ref(),
-            )
-          }
-        } else {
-          None
+      .filter_map(|pkg_id| metadata.packages.iter().find(|pkg| pkg.id == *pkg_id))
+      .filter(|pkg| !self.settings.binary_deps.contains_key(&pkg.name))
+      .filter_map(|pkg| workspace_member_path(pkg).map(|path| (pkg, path)))
+      .collect();
+
+    let workspace_members = member_packages
+      .iter()
+      .map(|(_, path)| path.clone())
+      .collect();
+
+    let workspace_member_packages = member_packages
+      .iter()
+      .map(|(pkg, path)| {
+        let node = metadata
+          .resolve
+          .as_ref()
+          .and_then(|resolve| resolve.nodes.iter().find(|node| node.id == pkg.id));
+
+        // Development dependencies are not part of what a member ships
+        let workspace_member_dependencies = node
+          .map(|node| {
+            node
+              .deps
+              .iter()
+              .filter(|dep| {
+                dep
+                  .dep_kinds
+                  .iter()
+                  .any(|info| info.kind != DependencyKind::Development)
+              })
+              .filter_map(|dep| {
+                member_packages
+                  .iter()
+                  .find(|(member, _)| member.id == dep.pkg)
+                  .map(|(_, path)| path.clone())
+              })
+              .sorted()
+              .collect()
+          })
+          .unwrap_or_default();
+
+        WorkspaceMemberPackage {
+          name: pkg.name.clone(),
+          version: pkg.version.clone(),
+          path: path.clone(),
+          workspace_member_dependencies,
         }
       })
       .collect();
@@ -125,6 +156,8 @@ impl<'planner> WorkspaceSubplanner<'planner> {
       gen_workspace_prefix: self.settings.gen_workspace_prefix.clone(),
       output_buildfile_suffix: self.settings.output_buildfile_suffix.clone(),
       workspace_members,
+      workspace_member_packages,
+      custom_platforms: self.settings.custom_platforms.clone(),
     }
   }
 
@@ -147,10 +180,12 @@ impl<'planner> WorkspaceSubplanner<'planner> {
       return None;
     }
 
-    // UNWRAP: Safe given unwrap during serialize step of metadata
+    // UNWRAP: Safe given unwrap during serialize step of metadata. Sparse registries are not
+    // understood by `SourceId` and are handled separately when producing source details.
     let own_source_id = own_package
       .source
       .as_ref()
+      .filter(|s| !registry::is_sparse(s))
       .map(|s| SourceId::from_url(&s.to_string()).unwrap());
 
     let crate_settings = self.crate_settings(own_package).ok()?;
@@ -169,6 +204,7 @@ impl<'planner> WorkspaceSubplanner<'planner> {
       crate_settings,
       sha256: &checksum_opt.map(|c| c.to_owned()),
       features: &self.metadata.features.get(&node.id),
+      binary_dep_source: self.metadata.binary_dep_sources.get(&own_package.name),
     };
 
     let res = crate_subplanner
@@ -313,9 +349,13 @@ impl<'planner> CrateSubplanner<'planner> {
       .into_iter()
       .map(|(target, deps)| {
         let target = target.unwrap();
-        let platform_targets = util::get_matching_bazel_triples(&target, &self.settings.targets)?
-          .map(|x| x.to_string())
-          .collect();
+        let platform_targets = util::get_matching_bazel_triples(
+          &target,
+          &self.settings.targets,
+          &self.settings.custom_platforms,
+        )?
+        .map(|x| x.to_string())
+        .collect();
 
         Ok(CrateTargetedDepContext {
           target,
@@ -392,6 +432,8 @@ impl<'planner> CrateSubplanner<'planner> {
       None => None,
     };
 
+    let platform_rustc_settings = self.produce_platform_rustc_settings(&raze_settings)?;
+
     let context = CrateContext {
       pkg_name: package.name.clone(),
       pkg_version: package.version.clone(),
@@ -406,26 +448,61 @@ impl<'planner> CrateSubplanner<'planner> {
       is_proc_macro,
       default_deps,
       targeted_deps,
+      platform_rustc_settings,
       workspace_path_to_crate: self.crate_catalog_entry.workspace_path(self.settings)?,
       build_script_target: build_script_target_opt,
       links: package.links.clone(),
+      homepage: package.homepage.clone(),
+      repository: package.repository.clone(),
       raze_settings,
       canonical_additional_build_file,
       source_details: self.produce_source_details(
         &self.settings.registry,
         package,
         &package_root,
-        self.settings.binary_deps.get(&package.name),
+        is_binary_dependency,
       )?,
       expected_build_path: self.crate_catalog_entry.local_build_path(self.settings)?,
       sha256: self.sha256.clone(),
       lib_target_name,
       targets,
+      license_files: self.produce_license_files(&manifest_path, &package_root),
+      package_root,
     };
 
     Ok(context)
   }
 
+  /// Resolves the crate's `targeted_rustc_settings` to the platforms they apply to. Settings of
+  /// every expression matching a platform are merged, in the order the expressions sort in.
+  fn produce_platform_rustc_settings(
+    &self,
+    raze_settings: &CrateSettings,
+  ) -> Result<BTreeMap<String, TargetedRustcSettings>> {
+    let mut platform_rustc_settings: BTreeMap<String, TargetedRustcSettings> = BTreeMap::new();
+    for (target, targeted_settings) in raze_settings.targeted_rustc_settings.iter() {
+      let platform_targets = util::get_matching_bazel_triples(
+        target,
+        &self.settings.targets,
+        &self.settings.custom_platforms,
+      )
+      .with_context(|| format!("Invalid targeted_rustc_settings expression: {}", target))?;
+
+      for platform_target in platform_targets {
+        let platform_settings = platform_rustc_settings
+          .entry(platform_target.to_owned())
+          .or_default();
+        platform_settings
+          .additional_flags
+          .extend(targeted_settings.additional_flags.iter().cloned());
+        platform_settings
+          .additional_env
+          .extend(targeted_settings.additional_env.clone());
+      }
+    }
+    Ok(platform_rustc_settings)
+  }
+
   /// Generates license data from internal crate details.
   fn produce_license(&self) -> LicenseData {
     let licenses_str = self
@@ -438,6 +515,56 @@ impl<'planner> CrateSubplanner<'planner> {
     license::get_license_from_str(licenses_str)
   }
 
+  /// Finds the license, copying and notice files of the crate, including any `license-file` it
+  /// declares. Paths are relative to the package root. Sources which can't be read are treated as
+  /// having no license files, as the files are informational.
+  fn produce_license_files(
+    &self,
+    manifest_path: &Utf8Path,
+    package_root: &Utf8Path,
+  ) -> Vec<Utf8PathBuf> {
+    // Files outside of the package are not available to Bazel
+    let (crate_root, package_root) =
+      match (manifest_path.parent(), package_root.canonicalize_utf8()) {
+        (Some(crate_root), Ok(package_root)) => (crate_root, package_root),
+        _ => return Vec::new(),
+      };
+
+    let mut license_files = Vec::new();
+    let entries = crate_root.read_dir_utf8().into_iter().flatten().flatten();
+    for entry in entries {
+      let file_name = entry.file_name().to_uppercase();
+      let is_license_file = ["LICENSE", "COPYING", "NOTICE"]
+        .iter()
+        .any(|prefix| file_name.starts_with(prefix));
+      if is_license_file && entry.path().is_file() {
+        license_files.push(entry.path().to_path_buf());
+      }
+    }
+
+    let package = self.crate_catalog_entry.package();
+    if let Some(license_file) = &package.license_file {
+      let license_file = crate_root.join(license_file);
+      if license_file.is_file() {
+        license_files.push(license_file);
+      }
+    }
+
+    let mut license_files: Vec<Utf8PathBuf> = license_files
+      .into_iter()
+      .filter_map(|path| path.canonicalize_utf8().ok())
+      .filter_map(|path| {
+        path
+          .strip_prefix(&package_root)
+          .map(Utf8Path::to_path_buf)
+          .ok()
+      })
+      .collect();
+    license_files.sort();
+    license_files.dedup();
+    license_files
+  }
+
   /// Generates the set of dependencies for the contained crate.
   fn produce_deps(&self) -> Result<DepProduction> {
     let mut dep_production = DepProduction::new();
@@ -605,7 +732,8 @@ impl<'planner> CrateSubplanner<'planner> {
             platform.matches(settings_target, platform_details.attrs())
           }
           None => {
-            util::is_bazel_supported_platform(platform) != util::BazelTargetSupport::Unsupported
+            util::is_bazel_supported_platform(platform, &self.settings.custom_platforms)
+              != util::BazelTargetSupport::Unsupported
           }
         }
       })
@@ -645,12 +773,22 @@ impl<'planner> CrateSubplanner<'planner> {
     crates_io_template: &str,
     package: &Package,
     package_root: &Utf8Path,
-    binary_dep_spec: Option<&cargo_toml::Dependency>,
+    is_binary_dependency: bool,
   ) -> Result<SourceDetails> {
     let mut git_data = None;
     let mut download_url = None;
 
-    if let Some(source_id) = self.source_id {
+    if let Some(source) = package.source.as_ref().filter(|s| registry::is_sparse(s)) {
+      download_url = Some(
+        Self::produce_download_url(crates_io_template, source, &package.name, &package.version)
+          .with_context(|| {
+            format!(
+              "Producing download URL for crate {} version {}",
+              &package.name, &package.version
+            )
+          })?,
+      );
+    } else if let Some(source_id) = self.source_id {
       if source_id.is_git() {
         let manifest_parent = package.manifest_path.parent().ok_or_else(|| {
           anyhow!(
@@ -704,23 +842,54 @@ impl<'planner> CrateSubplanner<'planner> {
           );
         }
       }
-    } else if let Some(binary_dep_spec) = binary_dep_spec {
-      if let Dependency::Detailed(detailed) = binary_dep_spec {
-        if detailed.registry.is_some() || detailed.registry_index.is_some() {
-          bail!("Binary deps do not support registries other than crates.io, but {} attempted to use a custom registry", package.name);
+    } else if is_binary_dependency {
+      // Binary dependencies are workspace members with no source of their own
+      let source = match self.binary_dep_source {
+        Some(binary_dep_source) => binary_dep_source.source.clone(),
+        None => Source {
+          repr: "registry+https://github.com/rust-lang/crates.io-index".to_owned(),
+        },
+      };
+      match SourceId::from_url(&source.repr) {
+        Ok(source_id) if source_id.is_git() => {
+          git_data = Some(GitRepo {
+            remote: source_id.url().to_string(),
+            commit: source_id
+              .precise()
+              .ok_or_else(|| {
+                anyhow!(
+                  "Expected binary dep {} to have a git commit, but it didn't",
+                  package.name
+                )
+              })?
+              .to_owned(),
+            path_to_crate_root: self
+              .binary_dep_source
+              .and_then(|binary_dep_source| binary_dep_source.path_to_crate_root.clone()),
+          });
+        }
+        _ => {
+          download_url = Some(
+            Self::produce_download_url(
+              crates_io_template,
+              &source,
+              &package.name,
+              &package.version,
+            )
+            .with_context(|| format!("Producing download URL for binary dep {}", package.name))?,
+          );
         }
       }
-      let source = Source {
-        repr: "registry+https://github.com/rust-lang/crates.io-index".to_owned(),
-      };
-      download_url = Some(
-        Self::produce_download_url(crates_io_template, &source, &package.name, &package.version)
-          .with_context(|| format!("Producing download URL for binary dep {}", package.name))?,
-      );
     }
+    let registry_auth = download_url
+      .as_ref()
+      .and_then(|url| self.settings.registry_auth_for(url))
+      .cloned();
+
     Ok(SourceDetails {
       git_data,
       download_url,
+      registry_auth,
     })
   }
 
@@ -730,7 +899,7 @@ impl<'planner> CrateSubplanner<'planner> {
     package_name: &str,
     package_version: &semver::Version,
   ) -> Result<Url> {
-    if source.is_crates_io() {
+    if registry::is_crates_io(source) {
       return Ok(
         crates_io_template
           .replace("{crate}", package_name)
@@ -738,23 +907,11 @@ impl<'planner> CrateSubplanner<'planner> {
           .parse()?,
       );
     }
-    if let Some(index_url) = source.repr.strip_prefix("registry+") {
-      let index = crates_index::BareIndex::from_url(index_url)?;
-      let index = index.open_or_clone()?;
-      let config = index.index_config()?;
-      let url = config.download_url(package_name, &package_version.to_string());
-      if let Some(url) = url {
-        return url.parse().context("Failed to parse index URL");
-      } else {
-        bail!(
-          "Could not derive URL for crate {} version {}",
-          package_name,
-          package_version
-        );
-      }
+    if let Some(index) = RegistryIndex::from_source(source) {
+      return index?.download_url(package_name, &package_version.to_string());
     }
     bail!(
-      "Expected source to be start with registry+ but was {:?}",
+      "Expected source to be a registry+ or sparse+ registry but was {:?}",
       source
     );
   }
@@ -811,6 +968,17 @@ impl<'planner> CrateSubplanner<'planner> {
         .trim_start_matches('/')
         .to_owned();
 
+      // Binary dependencies from git are fetched without the rest of their repository, but remote
+      // builds use its root
+      let path_to_crate_root = self
+        .binary_dep_source
+        .and_then(|source| source.path_to_crate_root.as_ref())
+        .filter(|_| self.settings.genmode == GenMode::Remote);
+      let package_root_path_str = match path_to_crate_root {
+        Some(path_to_crate_root) => format!("{}/{}", path_to_crate_root, package_root_path_str),
+        None => package_root_path_str,
+      };
+
       for kind in &target.kind {
         targets.push(BuildableTarget {
           name: target.name.clone(),
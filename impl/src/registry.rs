@@ -0,0 +1,288 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use camino::Utf8PathBuf;
+use cargo_metadata::Source;
+use crates_index::{BareIndex, Crate, Index, IndexConfig};
+use rustc_serialize::hex::ToHex;
+use url::Url;
+
+/// The prefix of the `source` of crates from a registry with a git index
+const GIT_REGISTRY_PREFIX: &str = "registry+";
+
+/// The prefix of the `source` of crates from a registry with a sparse index
+const SPARSE_REGISTRY_PREFIX: &str = "sparse+";
+
+/// The `source` of crates from crates.io when it is accessed through its sparse index
+const CRATES_IO_SPARSE_SOURCE: &str = "sparse+https://index.crates.io/";
+
+/// Returns true if the source is crates.io, through either its git or sparse index
+pub fn is_crates_io(source: &Source) -> bool {
+  source.is_crates_io() || source.repr == CRATES_IO_SPARSE_SOURCE
+}
+
+/// Returns true if the source is a registry served over Cargo's sparse HTTP protocol
+pub fn is_sparse(source: &Source) -> bool {
+  source.repr.starts_with(SPARSE_REGISTRY_PREFIX)
+}
+
+/// The index of a registry, which describes the versions of its crates and where to download them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryIndex {
+  /// A git repository, which is cloned into Cargo's home directory
+  Git(Url),
+  /// An HTTP server implementing Cargo's sparse protocol. The URL always ends with a `/`
+  Sparse(Url),
+  /// A checkout of an index in a local directory
+  Local(Utf8PathBuf),
+}
+
+impl RegistryIndex {
+  /// Parses the URL of an index, such as the `index_url` setting.
+  ///
+  /// URLs prefixed with `sparse+` are sparse indexes and `file://` URLs are local directories.
+  pub fn from_url(index_url: &str) -> Result<Self> {
+    if let Some(sparse_url) = index_url.strip_prefix(SPARSE_REGISTRY_PREFIX) {
+      return Self::sparse(sparse_url);
+    }
+
+    let url = Url::parse(index_url.trim_start_matches(GIT_REGISTRY_PREFIX))
+      .with_context(|| format!("Invalid crate index URL: {}", index_url))?;
+    if url.scheme().eq_ignore_ascii_case("file") {
+      return Ok(RegistryIndex::Local(Utf8PathBuf::from(url.path())));
+    }
+
+    Ok(RegistryIndex::Git(url))
+  }
+
+  /// Returns the index a package was published to, or None if it is not from a registry.
+  pub fn from_source(source: &Source) -> Option<Result<Self>> {
+    if let Some(sparse_url) = source.repr.strip_prefix(SPARSE_REGISTRY_PREFIX) {
+      return Some(Self::sparse(sparse_url));
+    }
+
+    // Package sources always refer to git indexes, even when they are on the local filesystem
+    source.repr.strip_prefix(GIT_REGISTRY_PREFIX).map(|url| {
+      Url::parse(url)
+        .map(RegistryIndex::Git)
+        .with_context(|| format!("Invalid crate index URL: {}", url))
+    })
+  }
+
+  fn sparse(url: &str) -> Result<Self> {
+    let mut url =
+      Url::parse(url).with_context(|| format!("Invalid sparse crate index URL: {}", url))?;
+    if !url.path().ends_with('/') {
+      url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(RegistryIndex::Sparse(url))
+  }
+
+  /// Returns true if the index can be read without network access
+  pub fn is_local(&self) -> bool {
+    match self {
+      RegistryIndex::Git(url) => BareIndex::from_url(url.as_str())
+        .map(|index| index.path().exists())
+        .unwrap_or(false),
+      RegistryIndex::Sparse(_) => false,
+      RegistryIndex::Local(_) => true,
+    }
+  }
+
+  /// Reads the index's `config.json` file
+  pub fn config(&self) -> Result<IndexConfig> {
+    match self {
+      RegistryIndex::Git(url) => Ok(
+        BareIndex::from_url(url.as_str())?
+          .open_or_clone()?
+          .index_config()?,
+      ),
+      RegistryIndex::Sparse(url) => {
+        let contents = fetch_sparse_file(url, "config.json")?;
+        serde_json::from_slice(&contents)
+          .with_context(|| format!("Failed to parse the config of crate index {}", url))
+      }
+      RegistryIndex::Local(path) => Ok(Index::new(path).index_config()?),
+    }
+  }
+
+  /// Reads the entry of a crate, which lists all of its published versions
+  pub fn crate_(&self, name: &str) -> Result<Crate> {
+    let crate_ = match self {
+      RegistryIndex::Git(url) => BareIndex::from_url(url.as_str())?
+        .open_or_clone()?
+        .crate_(name),
+      RegistryIndex::Sparse(url) => {
+        let path = sparse_crate_path(name)
+          .ok_or_else(|| anyhow!("Invalid crate name for a sparse index: {}", name))?;
+        Some(Crate::from_slice(&fetch_sparse_file(url, &path)?)?)
+      }
+      RegistryIndex::Local(path) => Index::new(path).crate_(name),
+    };
+
+    crate_.ok_or_else(|| anyhow!("Failed to find crate '{}' in index", name))
+  }
+
+  /// Derives the URL a crate can be downloaded from using the index's config
+  pub fn download_url(&self, name: &str, version: &str) -> Result<Url> {
+    let url = self.config()?.download_url(name, version).ok_or_else(|| {
+      anyhow!(
+        "Could not derive URL for crate {} version {}",
+        name,
+        version
+      )
+    })?;
+
+    url.parse().context("Failed to parse index URL")
+  }
+
+  /// Looks up the sha256 checksum of a crate's version
+  pub fn checksum(&self, name: &str, version: &str) -> Result<String> {
+    let crate_ = self.crate_(name)?;
+    let crate_version = crate_
+      .versions()
+      .iter()
+      .find(|ver| ver.version() == version)
+      .ok_or_else(|| anyhow!("Failed to find version {} for crate {}", version, name))?;
+
+    Ok(crate_version.checksum()[..].to_hex())
+  }
+}
+
+impl fmt::Display for RegistryIndex {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RegistryIndex::Git(url) => write!(f, "{}", url),
+      RegistryIndex::Sparse(url) => write!(f, "{}{}", SPARSE_REGISTRY_PREFIX, url),
+      RegistryIndex::Local(path) => write!(f, "{}", path),
+    }
+  }
+}
+
+/// Returns the path of a crate's entry relative to the root of a sparse index
+fn sparse_crate_path(name: &str) -> Option<String> {
+  if !name.is_ascii() {
+    return None;
+  }
+
+  let name = name.to_ascii_lowercase();
+  let prefix = match name.len() {
+    0 => return None,
+    1 => "1".to_owned(),
+    2 => "2".to_owned(),
+    3 => format!("3/{}", &name[..1]),
+    _ => format!("{}/{}", &name[..2], &name[2..4]),
+  };
+
+  Some(format!("{}/{}", prefix, name))
+}
+
+/// Downloads a file from a sparse index
+fn fetch_sparse_file(index_url: &Url, path: &str) -> Result<Vec<u8>> {
+  let url = index_url.join(path)?;
+  let response = reqwest::blocking::get(url.clone())
+    .and_then(|response| response.error_for_status())
+    .with_context(|| format!("Failed to fetch {}", url))?;
+
+  Ok(response.bytes()?.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+  use httpmock::MockServer;
+
+  use crate::testing::mock_sparse_index;
+
+  use super::*;
+
+  #[test]
+  fn test_parse_index_urls() {
+    assert_eq!(
+      RegistryIndex::from_url("sparse+https://example.com/index").unwrap(),
+      RegistryIndex::Sparse(Url::parse("https://example.com/index/").unwrap())
+    );
+    assert_eq!(
+      RegistryIndex::from_url("https://github.com/rust-lang/crates.io-index").unwrap(),
+      RegistryIndex::Git(Url::parse("https://github.com/rust-lang/crates.io-index").unwrap())
+    );
+    assert_eq!(
+      RegistryIndex::from_url("file:///some/index").unwrap(),
+      RegistryIndex::Local(Utf8PathBuf::from("/some/index"))
+    );
+
+    let source = Source {
+      repr: "registry+file:///some/index".to_owned(),
+    };
+    assert_eq!(
+      RegistryIndex::from_source(&source).unwrap().unwrap(),
+      RegistryIndex::Git(Url::parse("file:///some/index").unwrap())
+    );
+    let source = Source {
+      repr: "git+https://github.com/example/repo#abcdef".to_owned(),
+    };
+    assert!(RegistryIndex::from_source(&source).is_none());
+  }
+
+  #[test]
+  fn test_sparse_crate_path() {
+    assert_eq!(sparse_crate_path("a").unwrap(), "1/a");
+    assert_eq!(sparse_crate_path("ab").unwrap(), "2/ab");
+    assert_eq!(sparse_crate_path("abc").unwrap(), "3/a/abc");
+    assert_eq!(sparse_crate_path("Serde").unwrap(), "se/rd/serde");
+    assert!(sparse_crate_path("").is_none());
+  }
+
+  #[test]
+  fn test_crates_io_sources() {
+    for repr in &[
+      "registry+https://github.com/rust-lang/crates.io-index",
+      "sparse+https://index.crates.io/",
+    ] {
+      let source = Source {
+        repr: repr.to_string(),
+      };
+      assert!(is_crates_io(&source));
+    }
+  }
+
+  #[test]
+  fn test_sparse_index_download_url_and_checksum() {
+    let mock_server = MockServer::start();
+    let mocks = mock_sparse_index(
+      &mock_server,
+      &format!("{}/dl/{{crate}}/{{version}}.crate", mock_server.base_url()),
+      "some-crate",
+      &["1.0.0", "1.1.0"],
+    );
+    let index = RegistryIndex::from_url(&format!("sparse+{}", mock_server.base_url())).unwrap();
+
+    assert_eq!(
+      index.download_url("some-crate", "1.1.0").unwrap().as_str(),
+      format!("{}/dl/some-crate/1.1.0.crate", mock_server.base_url())
+    );
+    assert_eq!(
+      index.checksum("some-crate", "1.1.0").unwrap(),
+      "1100000000000000000000000000000000000000000000000000000000000000"
+    );
+    assert!(index.checksum("some-crate", "2.0.0").is_err());
+
+    let (config_mock, crate_mock) = (&mocks[0], &mocks[1]);
+    config_mock.assert();
+    crate_mock.assert_hits(2);
+  }
+}
@@ -13,10 +13,25 @@
 // limitations under the License.
 
 pub mod bazel;
+pub mod cache;
+pub mod json;
+pub mod licenses;
+pub mod sbom;
 
-use crate::planning::PlannedBuild;
+use crate::{
+  planning::PlannedBuild,
+  settings::{LicenseReportFormat, SbomFormat},
+};
 use anyhow::Result;
-use camino::Utf8PathBuf;
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use itertools::Itertools;
+use std::fs;
+
+/// The number of unchanged lines surrounding each change in a rendered diff
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// The name of the file which records the outputs of the last run of cargo-raze
+pub const OUTPUT_MANIFEST_NAME: &str = ".cargo-raze-manifest";
 
 pub trait BuildRenderer {
   fn render_planned_build(
@@ -47,4 +62,475 @@ pub struct RenderDetails {
   pub rust_rules_workspace_name: String,
   pub experimental_api: bool,
   pub render_package_aliases: bool,
+  pub render_module_extension: bool,
+  pub license_report: Option<LicenseReportFormat>,
+  pub render_license_targets: bool,
+  pub sbom_formats: Vec<SbomFormat>,
+}
+
+/// A rendered file whose contents differ from what is currently on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleFile {
+  pub path: Utf8PathBuf,
+  /// A unified diff from the contents on disk to the expected contents
+  pub diff: String,
+}
+
+/// Compares rendered outputs against the filesystem, returning a diff for each out of date file.
+///
+/// `orphaned_paths` are files which exist on disk but would be removed by a regular run. Each of
+/// these is reported as stale with a diff removing all of its contents. Paths in the diffs are
+/// displayed relative to `root`.
+pub fn find_stale_files(
+  file_outputs: &[FileOutputs],
+  orphaned_paths: &[Utf8PathBuf],
+  root: &Utf8Path,
+) -> Result<Vec<StaleFile>> {
+  let mut stale_files = Vec::new();
+
+  for output in file_outputs.iter() {
+    let existing_contents = if output.path.exists() {
+      Some(fs::read_to_string(&output.path)?)
+    } else {
+      None
+    };
+
+    if existing_contents.as_deref() == Some(output.contents.as_str()) {
+      continue;
+    }
+
+    stale_files.push(StaleFile {
+      path: output.path.clone(),
+      diff: unified_diff(
+        output.path.strip_prefix(root).unwrap_or(&output.path),
+        existing_contents.as_deref(),
+        Some(&output.contents),
+      ),
+    });
+  }
+
+  for path in orphaned_paths.iter() {
+    if file_outputs.iter().any(|output| &output.path == path) {
+      continue;
+    }
+
+    let existing_contents = fs::read_to_string(path)?;
+    stale_files.push(StaleFile {
+      path: path.clone(),
+      diff: unified_diff(
+        path.strip_prefix(root).unwrap_or(path),
+        Some(&existing_contents),
+        None,
+      ),
+    });
+  }
+
+  stale_files.sort_by(|a, b| a.path.cmp(&b.path));
+  Ok(stale_files)
+}
+
+/// Produces a manifest listing the paths of all file outputs relative to `root`.
+///
+/// The manifest is read by subsequent runs to identify files that are no longer generated.
+pub fn render_output_manifest(
+  file_outputs: &[FileOutputs],
+  manifest_path: &Utf8Path,
+  root: &Utf8Path,
+) -> FileOutputs {
+  let mut contents = String::from(
+    "# @generated by cargo-raze. Lists the files written by the last run so stale files can be \
+     pruned.\n",
+  );
+  for output in file_outputs.iter() {
+    let path = output.path.strip_prefix(root).unwrap_or(&output.path);
+    contents += &format!("{}\n", path);
+  }
+
+  FileOutputs {
+    path: manifest_path.to_path_buf(),
+    contents,
+  }
+}
+
+/// Finds files listed in a previous output manifest which still exist but are no longer rendered.
+///
+/// Manifest entries are relative to `root`, and only files within the directory containing the
+/// manifest are considered. Absolute paths, paths containing `..`, and paths which resolve to
+/// elsewhere through symlinks are ignored with a warning so they are never deleted.
+pub fn find_orphaned_files(
+  file_outputs: &[FileOutputs],
+  manifest_path: &Utf8Path,
+  root: &Utf8Path,
+) -> Result<Vec<Utf8PathBuf>> {
+  if !manifest_path.exists() {
+    return Ok(Vec::new());
+  }
+
+  // UNWRAP: The manifest exists, so it has a parent directory
+  let output_dir = manifest_path.parent().unwrap().canonicalize_utf8()?;
+  let manifest = fs::read_to_string(manifest_path)?;
+  let orphaned_files = manifest
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .filter(|line| {
+      let is_relative = Utf8Path::new(line)
+        .components()
+        .all(|component| matches!(component, Utf8Component::Normal(_) | Utf8Component::CurDir));
+      if !is_relative {
+        eprintln!(
+          "WARNING: Ignoring `{}` in {}. Entries must be relative paths without `..`",
+          line, manifest_path
+        );
+      }
+      is_relative
+    })
+    .map(|line| root.join(line))
+    .filter(|path| path != manifest_path)
+    .filter(|path| !file_outputs.iter().any(|output| &output.path == path))
+    .filter(|path| path.exists())
+    .filter(|path| {
+      let is_contained = matches!(
+        path.canonicalize_utf8(),
+        Ok(canonical_path) if canonical_path.starts_with(&output_dir)
+      );
+      if !is_contained {
+        eprintln!(
+          "WARNING: Ignoring `{}` in {}. It is outside of {}",
+          path, manifest_path, output_dir
+        );
+      }
+      is_contained
+    })
+    .sorted()
+    .dedup()
+    .collect();
+
+  Ok(orphaned_files)
+}
+
+/// Deletes orphaned files, returning the paths that were removed.
+///
+/// When an orphaned file lives directly within a crate's directory in `vendor_dir` and no other
+/// rendered output remains there, the crate is no longer a dependency and the whole directory is
+/// removed.
+pub fn prune_orphaned_files(
+  orphaned_files: &[Utf8PathBuf],
+  file_outputs: &[FileOutputs],
+  vendor_dir: Option<&Utf8Path>,
+) -> Result<Vec<Utf8PathBuf>> {
+  let mut removed = Vec::new();
+  for path in orphaned_files.iter() {
+    let crate_dir = path
+      .parent()
+      .filter(|parent| vendor_dir.is_some() && parent.parent() == vendor_dir);
+
+    match crate_dir {
+      Some(crate_dir)
+        if !file_outputs
+          .iter()
+          .any(|output| output.path.starts_with(crate_dir)) =>
+      {
+        if crate_dir.exists() {
+          fs::remove_dir_all(crate_dir)?;
+          removed.push(crate_dir.to_path_buf());
+        }
+      }
+      _ => {
+        if path.exists() {
+          fs::remove_file(path)?;
+          removed.push(path.clone());
+        }
+      }
+    }
+  }
+
+  Ok(removed)
+}
+
+/// Renders a unified diff between two versions of a file. `None` represents a missing file.
+fn unified_diff(path: &Utf8Path, old: Option<&str>, new: Option<&str>) -> String {
+  let old_lines: Vec<&str> = old.map(|s| s.lines().collect()).unwrap_or_default();
+  let new_lines: Vec<&str> = new.map(|s| s.lines().collect()).unwrap_or_default();
+
+  // Each entry is a change marker, the line, and its 1-based position in the old and new files
+  let mut lines: Vec<(char, &str, usize, usize)> = Vec::new();
+  let (mut old_pos, mut new_pos) = (0, 0);
+  for result in diff::slice(&old_lines, &new_lines) {
+    match result {
+      diff::Result::Both(line, _) => {
+        old_pos += 1;
+        new_pos += 1;
+        lines.push((' ', line, old_pos, new_pos));
+      }
+      diff::Result::Left(line) => {
+        old_pos += 1;
+        lines.push(('-', line, old_pos, new_pos));
+      }
+      diff::Result::Right(line) => {
+        new_pos += 1;
+        lines.push(('+', line, old_pos, new_pos));
+      }
+    }
+  }
+
+  let mut output = format!(
+    "--- {}\n+++ {}\n",
+    old.map_or("/dev/null".to_owned(), |_| format!("a/{}", path)),
+    new.map_or("/dev/null".to_owned(), |_| format!("b/{}", path)),
+  );
+
+  // Group changed lines into hunks, merging any that share context
+  let changes: Vec<usize> = lines
+    .iter()
+    .enumerate()
+    .filter(|(_, line)| line.0 != ' ')
+    .map(|(idx, _)| idx)
+    .collect();
+
+  let mut hunks: Vec<(usize, usize)> = Vec::new();
+  for idx in changes {
+    let start = idx.saturating_sub(DIFF_CONTEXT_LINES);
+    let end = (idx + DIFF_CONTEXT_LINES + 1).min(lines.len());
+    match hunks.last_mut() {
+      Some(last) if start <= last.1 => last.1 = end,
+      _ => hunks.push((start, end)),
+    }
+  }
+
+  for (start, end) in hunks {
+    let hunk = &lines[start..end];
+    let old_count = hunk.iter().filter(|line| line.0 != '+').count();
+    let new_count = hunk.iter().filter(|line| line.0 != '-').count();
+
+    // Unified diffs start empty ranges at the line preceding them
+    let old_start = hunk
+      .iter()
+      .find(|line| line.0 != '+')
+      .map_or(hunk[0].2, |line| line.2);
+    let new_start = hunk
+      .iter()
+      .find(|line| line.0 != '-')
+      .map_or(hunk[0].3, |line| line.3);
+
+    output += &format!(
+      "@@ -{},{} +{},{} @@\n",
+      old_start, old_count, new_start, new_count
+    );
+    for (marker, line, _, _) in hunk {
+      output += &format!("{}{}\n", marker, line);
+    }
+  }
+
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use tempfile::TempDir;
+
+  use crate::testing::utf8_path;
+
+  #[test]
+  fn test_unified_diff_reports_changed_lines() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let new = "a\nb\nc\nd\nE\nf\ng\nh\n";
+
+    assert_eq!(
+      unified_diff(Utf8Path::new("BUILD.bazel"), Some(old), Some(new)),
+      indoc::indoc! { r#"
+        --- a/BUILD.bazel
+        +++ b/BUILD.bazel
+        @@ -2,7 +2,7 @@
+         b
+         c
+         d
+        -e
+        +E
+         f
+         g
+         h
+      "# }
+    );
+  }
+
+  #[test]
+  fn test_unified_diff_of_new_file() {
+    assert_eq!(
+      unified_diff(Utf8Path::new("BUILD.bazel"), None, Some("a\nb\n")),
+      indoc::indoc! { r#"
+        --- /dev/null
+        +++ b/BUILD.bazel
+        @@ -0,0 +1,2 @@
+        +a
+        +b
+      "# }
+    );
+  }
+
+  #[test]
+  fn test_find_stale_files() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref());
+
+    let up_to_date = root.join("up_to_date.bazel");
+    let changed = root.join("changed.bazel");
+    let missing = root.join("missing.bazel");
+    let orphaned = root.join("orphaned.bazel");
+    fs::write(&up_to_date, "same\n").unwrap();
+    fs::write(&changed, "old\n").unwrap();
+    fs::write(&orphaned, "orphaned\n").unwrap();
+
+    let file_outputs = vec![
+      FileOutputs {
+        path: up_to_date,
+        contents: "same\n".to_owned(),
+      },
+      FileOutputs {
+        path: changed.clone(),
+        contents: "new\n".to_owned(),
+      },
+      FileOutputs {
+        path: missing.clone(),
+        contents: "new\n".to_owned(),
+      },
+    ];
+
+    let orphaned_paths = vec![orphaned.clone()];
+    let stale_paths: Vec<Utf8PathBuf> = find_stale_files(&file_outputs, &orphaned_paths, root)
+      .unwrap()
+      .into_iter()
+      .map(|stale| stale.path)
+      .collect();
+
+    assert_eq!(stale_paths, vec![changed, missing, orphaned]);
+  }
+
+  #[test]
+  fn test_find_orphaned_files_from_manifest() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref());
+    let manifest_path = root.join("cargo").join(OUTPUT_MANIFEST_NAME);
+
+    let kept = root.join("cargo/vendor/kept-1.0.0/BUILD.bazel");
+    let removed = root.join("cargo/vendor/removed-1.0.0/BUILD.bazel");
+    let deleted = root.join("cargo/vendor/deleted-1.0.0/BUILD.bazel");
+    for path in [&kept, &removed] {
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, "").unwrap();
+    }
+
+    let previous_outputs = vec![
+      FileOutputs {
+        path: kept.clone(),
+        contents: String::new(),
+      },
+      FileOutputs {
+        path: removed.clone(),
+        contents: String::new(),
+      },
+      FileOutputs {
+        path: deleted,
+        contents: String::new(),
+      },
+    ];
+    let manifest = render_output_manifest(&previous_outputs, &manifest_path, root);
+    assert!(manifest
+      .contents
+      .contains("cargo/vendor/removed-1.0.0/BUILD.bazel\n"));
+    fs::write(&manifest.path, &manifest.contents).unwrap();
+
+    // Only files which still exist on disk and are no longer rendered are orphaned
+    let current_outputs = vec![FileOutputs {
+      path: kept,
+      contents: String::new(),
+    }];
+    assert_eq!(
+      find_orphaned_files(&current_outputs, &manifest_path, root).unwrap(),
+      vec![removed]
+    );
+  }
+
+  #[test]
+  fn test_find_orphaned_files_ignores_paths_outside_output_dir() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref()).join("workspace");
+    let manifest_path = root.join("cargo").join(OUTPUT_MANIFEST_NAME);
+
+    let outside = utf8_path(dir.as_ref()).join("precious.txt");
+    let sibling = root.join("src/lib.rs");
+    let inside = root.join("cargo/remote/BUILD.old-1.0.0.bazel");
+    for path in [&outside, &sibling, &inside] {
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, "").unwrap();
+    }
+    fs::write(
+      &manifest_path,
+      format!(
+        "../precious.txt\ncargo/../../precious.txt\n{}\nsrc/lib.rs\ncargo/remote/BUILD.old-1.0.0.bazel\n",
+        outside
+      ),
+    )
+    .unwrap();
+
+    assert_eq!(
+      find_orphaned_files(&[], &manifest_path, &root).unwrap(),
+      vec![inside]
+    );
+
+    prune_orphaned_files(
+      &find_orphaned_files(&[], &manifest_path, &root).unwrap(),
+      &[],
+      None,
+    )
+    .unwrap();
+    assert!(outside.exists());
+    assert!(sibling.exists());
+  }
+
+  #[test]
+  fn test_prune_orphaned_files_removes_vendored_crate_dirs() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref());
+    let vendor_dir = root.join("cargo/vendor");
+
+    let removed_crate = vendor_dir.join("removed-1.0.0");
+    let renamed_build_file = vendor_dir.join("kept-1.0.0/BUILD");
+    let stale_alias = root.join("member/cargo/BUILD.bazel");
+    for path in [
+      &removed_crate.join("BUILD.bazel"),
+      &removed_crate.join("src/lib.rs"),
+      &renamed_build_file,
+      &stale_alias,
+    ] {
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, "").unwrap();
+    }
+
+    // The `kept` crate is still rendered, but with a different BUILD file name
+    let file_outputs = vec![FileOutputs {
+      path: vendor_dir.join("kept-1.0.0/BUILD.bazel"),
+      contents: String::new(),
+    }];
+
+    let removed = prune_orphaned_files(
+      &[
+        removed_crate.join("BUILD.bazel"),
+        renamed_build_file.clone(),
+        stale_alias.clone(),
+      ],
+      &file_outputs,
+      Some(&vendor_dir),
+    )
+    .unwrap();
+
+    assert_eq!(
+      removed,
+      vec![removed_crate.clone(), renamed_build_file, stale_alias]
+    );
+    assert!(!removed_crate.exists());
+    assert!(vendor_dir.join("kept-1.0.0").exists());
+  }
 }
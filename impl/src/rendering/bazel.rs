@@ -21,7 +21,13 @@ use crate::{
   context::{CrateContext, DependencyAlias, WorkspaceContext},
   error::RazeError,
   planning::PlannedBuild,
-  rendering::{BuildRenderer, FileOutputs, RenderDetails},
+  rendering::{
+    cache::{content_hash, RenderCache, RENDER_CACHE_NAME},
+    licenses::{code_fence_filter, license_report_file_name, LicenseReport, LICENSES_PACKAGE_NAME},
+    sbom::{self, SBOM_PACKAGE_NAME},
+    BuildRenderer, FileOutputs, RenderDetails,
+  },
+  settings::LicenseReportFormat,
 };
 
 use std::error::Error;
@@ -58,9 +64,102 @@ filegroup(
 )
 "#;
 
+/// The templates used to render Bazel files, keyed by name
+const TEMPLATES: &[(&str, &str)] = &[
+  (
+    "templates/crate.BUILD.template",
+    include_str!("templates/crate.BUILD.template"),
+  ),
+  (
+    "templates/partials/build_script.template",
+    include_str!("templates/partials/build_script.template"),
+  ),
+  (
+    "templates/partials/common_attrs.template",
+    include_str!("templates/partials/common_attrs.template"),
+  ),
+  (
+    "templates/partials/crates_macro.template",
+    include_str!("templates/partials/crates_macro.template"),
+  ),
+  (
+    "templates/partials/header.template",
+    include_str!("templates/partials/header.template"),
+  ),
+  (
+    "templates/partials/remote_crate_repository.template",
+    include_str!("templates/partials/remote_crate_repository.template"),
+  ),
+  (
+    "templates/partials/remote_crates_patch.template",
+    include_str!("templates/partials/remote_crates_patch.template"),
+  ),
+  (
+    "templates/partials/rust_binary.template",
+    include_str!("templates/partials/rust_binary.template"),
+  ),
+  (
+    "templates/partials/rust_library.template",
+    include_str!("templates/partials/rust_library.template"),
+  ),
+  (
+    "templates/partials/rust_proc_macro.template",
+    include_str!("templates/partials/rust_proc_macro.template"),
+  ),
+  (
+    "templates/partials/rust_test.template",
+    include_str!("templates/partials/rust_test.template"),
+  ),
+  (
+    "templates/partials/rustc_env.template",
+    include_str!("templates/partials/rustc_env.template"),
+  ),
+  (
+    "templates/partials/rustc_flags.template",
+    include_str!("templates/partials/rustc_flags.template"),
+  ),
+  (
+    "templates/partials/targeted_dependencies.template",
+    include_str!("templates/partials/targeted_dependencies.template"),
+  ),
+  (
+    "templates/licenses.BUILD.template",
+    include_str!("templates/licenses.BUILD.template"),
+  ),
+  (
+    "templates/sbom.BUILD.template",
+    include_str!("templates/sbom.BUILD.template"),
+  ),
+  (
+    "templates/module_extension.bzl.template",
+    include_str!("templates/module_extension.bzl.template"),
+  ),
+  (
+    "templates/remote_crates.bzl.template",
+    include_str!("templates/remote_crates.bzl.template"),
+  ),
+  (
+    "templates/third_party_licenses.html.template",
+    include_str!("templates/third_party_licenses.html.template"),
+  ),
+  (
+    "templates/third_party_licenses.md.template",
+    include_str!("templates/third_party_licenses.md.template"),
+  ),
+  (
+    "templates/workspace.BUILD.template",
+    include_str!("templates/workspace.BUILD.template"),
+  ),
+  (
+    "templates/partials/features.template",
+    include_str!("templates/partials/features.template"),
+  ),
+];
+
 #[derive(Default)]
 pub struct BazelRenderer {
   internal_renderer: Tera,
+  render_cache: Option<RenderCache>,
 }
 
 /// Generate the expected Bazel package name
@@ -77,75 +176,49 @@ impl BazelRenderer {
     // Configure tera with a bogus template dir: We don't want any runtime template support
     let mut internal_renderer = Tera::new("/tmp/cargo-raze/doesnt/exist/*").unwrap();
     internal_renderer
-      .add_raw_templates(vec![
-        (
-          "templates/crate.BUILD.template",
-          include_str!("templates/crate.BUILD.template"),
-        ),
-        (
-          "templates/partials/build_script.template",
-          include_str!("templates/partials/build_script.template"),
-        ),
-        (
-          "templates/partials/common_attrs.template",
-          include_str!("templates/partials/common_attrs.template"),
-        ),
-        (
-          "templates/partials/crates_macro.template",
-          include_str!("templates/partials/crates_macro.template"),
-        ),
-        (
-          "templates/partials/header.template",
-          include_str!("templates/partials/header.template"),
-        ),
-        (
-          "templates/partials/remote_crates_patch.template",
-          include_str!("templates/partials/remote_crates_patch.template"),
-        ),
-        (
-          "templates/partials/rust_binary.template",
-          include_str!("templates/partials/rust_binary.template"),
-        ),
-        (
-          "templates/partials/rust_library.template",
-          include_str!("templates/partials/rust_library.template"),
-        ),
-        (
-          "templates/partials/rust_proc_macro.template",
-          include_str!("templates/partials/rust_proc_macro.template"),
-        ),
-        (
-          "templates/partials/targeted_dependencies.template",
-          include_str!("templates/partials/targeted_dependencies.template"),
-        ),
-        (
-          "templates/remote_crates.bzl.template",
-          include_str!("templates/remote_crates.bzl.template"),
-        ),
-        (
-          "templates/workspace.BUILD.template",
-          include_str!("templates/workspace.BUILD.template"),
-        ),
-        (
-          "templates/partials/features.template",
-          include_str!("templates/partials/features.template"),
-        ),
-      ])
+      .add_raw_templates(TEMPLATES.to_vec())
       .unwrap();
+    internal_renderer.register_filter("code_fence", code_fence_filter);
 
-    Self { internal_renderer }
+    Self {
+      internal_renderer,
+      render_cache: None,
+    }
+  }
+
+  /// Reuses the existing BUILD files of crates whose inputs are unchanged since the cache was
+  /// written, and renders an updated cache alongside the other outputs.
+  pub fn with_render_cache(mut self, render_cache: RenderCache) -> Self {
+    self.render_cache = Some(render_cache);
+    self
+  }
+
+  /// The cache in use, if any, which tracks how many crates were skipped or regenerated.
+  pub fn render_cache(&self) -> Option<&RenderCache> {
+    self.render_cache.as_ref()
   }
 
   pub fn render_crate(
     &self,
     workspace_context: &WorkspaceContext,
     package: &CrateContext,
-    rust_rules_workspace_name: &str,
+    render_details: &RenderDetails,
   ) -> Result<String, tera::Error> {
     let mut context = Context::new();
     context.insert("workspace", &workspace_context);
     context.insert("crate", &package);
-    context.insert("rust_rules_workspace_name", rust_rules_workspace_name);
+    context.insert(
+      "rust_rules_workspace_name",
+      &render_details.rust_rules_workspace_name,
+    );
+    context.insert(
+      "render_license_files",
+      &render_details.license_report.is_some(),
+    );
+    context.insert(
+      "render_license_targets",
+      &render_details.render_license_targets,
+    );
     self
       .internal_renderer
       .render("templates/crate.BUILD.template", &context)
@@ -170,15 +243,9 @@ impl BazelRenderer {
     &self,
     workspace_context: &WorkspaceContext,
     package: &CrateContext,
-    rust_rules_workspace_name: &str,
+    render_details: &RenderDetails,
   ) -> Result<String, tera::Error> {
-    let mut context = Context::new();
-    context.insert("workspace", &workspace_context);
-    context.insert("crate", &package);
-    context.insert("rust_rules_workspace_name", rust_rules_workspace_name);
-    self
-      .internal_renderer
-      .render("templates/crate.BUILD.template", &context)
+    self.render_crate(workspace_context, package, render_details)
   }
 
   pub fn render_crates_bzl(
@@ -194,12 +261,27 @@ impl BazelRenderer {
     context.insert("crates", &all_packages);
     context.insert("bazel_package_name", &bazel_package_name);
     context.insert("is_remote_genmode", &is_remote_genmode);
+    context.insert("is_module_extension", &false);
     context.insert("experimental_api", &experimental_api);
     self
       .internal_renderer
       .render("templates/remote_crates.bzl.template", &context)
   }
 
+  pub fn render_module_extension(
+    &self,
+    workspace_context: &WorkspaceContext,
+    all_packages: &[CrateContext],
+  ) -> Result<String, tera::Error> {
+    let mut context = Context::new();
+    context.insert("workspace", &workspace_context);
+    context.insert("crates", &all_packages);
+    context.insert("is_module_extension", &true);
+    self
+      .internal_renderer
+      .render("templates/module_extension.bzl.template", &context)
+  }
+
   pub fn render_aliases(
     &self,
     planned_build: &PlannedBuild,
@@ -244,6 +326,154 @@ impl BazelRenderer {
     Ok(file_outputs)
   }
 
+  /// Renders the BUILD file of a crate, or reuses the existing one if its inputs are unchanged.
+  fn render_crate_build_file(
+    &mut self,
+    workspace_context: &WorkspaceContext,
+    package: &CrateContext,
+    render_details: &RenderDetails,
+    build_file_path: &Utf8Path,
+    is_remote_genmode: bool,
+  ) -> Result<String> {
+    let inputs_hash = match self.render_cache.as_mut() {
+      Some(render_cache) => {
+        let hash = crate_render_hash(
+          workspace_context,
+          package,
+          render_details,
+          is_remote_genmode,
+        )?;
+        if let Some(existing_contents) = render_cache.lookup(build_file_path, &hash) {
+          return Ok(existing_contents);
+        }
+        Some(hash)
+      }
+      None => None,
+    };
+
+    let rendered_crate_build_file = if is_remote_genmode {
+      self.render_remote_crate(workspace_context, package, render_details)
+    } else {
+      self.render_crate(workspace_context, package, render_details)
+    }
+    .map_err(|e| RazeError::Rendering {
+      crate_name_opt: Some(package.pkg_name.to_owned()),
+      message: unwind_tera_error!(e),
+    })?;
+
+    let contents = include_additional_build_file(package, rendered_crate_build_file)?;
+    if let (Some(render_cache), Some(hash)) = (self.render_cache.as_mut(), inputs_hash) {
+      render_cache.record(build_file_path, &hash, &contents);
+    }
+    Ok(contents)
+  }
+
+  /// Renders an attribution document of the licenses of all crates, along with a package exposing
+  /// their license files, if a license report was requested.
+  fn render_license_report(
+    &self,
+    path_prefix: &Utf8Path,
+    render_details: &RenderDetails,
+    crate_contexts: &[CrateContext],
+  ) -> Result<Vec<FileOutputs>> {
+    let format = match render_details.license_report {
+      Some(format) => format,
+      None => return Ok(Vec::new()),
+    };
+
+    let report = LicenseReport::new(crate_contexts)?;
+    let report_file_name = license_report_file_name(format);
+    let mut context = Context::new();
+    context.insert("report", &report);
+    let report_contents = match format {
+      LicenseReportFormat::Json => {
+        let mut contents = serde_json::to_string_pretty(&report)?;
+        contents.push('\n');
+        Ok(contents)
+      }
+      LicenseReportFormat::Markdown => self
+        .internal_renderer
+        .render("templates/third_party_licenses.md.template", &context),
+      LicenseReportFormat::Html => self
+        .internal_renderer
+        .render("templates/third_party_licenses.html.template", &context),
+    }
+    .map_err(|e| RazeError::Rendering {
+      crate_name_opt: None,
+      message: unwind_tera_error!(e),
+    })?;
+
+    let mut context = Context::new();
+    context.insert("crates", &crate_contexts);
+    context.insert("report_file_name", report_file_name);
+    let build_file_contents = self
+      .internal_renderer
+      .render("templates/licenses.BUILD.template", &context)
+      .map_err(|e| RazeError::Rendering {
+        crate_name_opt: None,
+        message: unwind_tera_error!(e),
+      })?;
+
+    let licenses_package = path_prefix.join(LICENSES_PACKAGE_NAME);
+    Ok(vec![
+      FileOutputs {
+        path: licenses_package.join(report_file_name),
+        contents: report_contents,
+      },
+      FileOutputs {
+        path: licenses_package.join("BUILD.bazel"),
+        contents: build_file_contents,
+      },
+    ])
+  }
+
+  /// Renders the requested SBOMs of each workspace member, along with a package exporting them.
+  fn render_sboms(
+    &self,
+    path_prefix: &Utf8Path,
+    render_details: &RenderDetails,
+    planned_build: &PlannedBuild,
+  ) -> Result<Vec<FileOutputs>> {
+    if render_details.sbom_formats.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let sbom_package = path_prefix.join(SBOM_PACKAGE_NAME);
+    let mut file_outputs =
+      sbom::render_sboms(planned_build, &render_details.sbom_formats, &sbom_package)?;
+
+    let mut file_names: Vec<&str> = file_outputs
+      .iter()
+      .filter_map(|output| output.path.file_name())
+      .collect();
+    file_names.sort_unstable();
+    let mut context = Context::new();
+    context.insert("file_names", &file_names);
+    let build_file_contents = self
+      .internal_renderer
+      .render("templates/sbom.BUILD.template", &context)
+      .map_err(|e| RazeError::Rendering {
+        crate_name_opt: None,
+        message: unwind_tera_error!(e),
+      })?;
+
+    file_outputs.push(FileOutputs {
+      path: sbom_package.join("BUILD.bazel"),
+      contents: build_file_contents,
+    });
+    Ok(file_outputs)
+  }
+
+  /// Renders the cache of crate input hashes, if one is in use.
+  fn render_cache_file(&self, path_prefix: &Utf8Path) -> Result<Option<FileOutputs>> {
+    match &self.render_cache {
+      Some(render_cache) => Ok(Some(
+        render_cache.render(&path_prefix.join(RENDER_CACHE_NAME))?,
+      )),
+      None => Ok(None),
+    }
+  }
+
   fn render_crates_bzl_package_file(
     &self,
     path_prefix: &Utf8Path,
@@ -271,6 +501,33 @@ impl BazelRenderer {
   }
 }
 
+/// Computes a hash of everything that affects the contents of a crate's BUILD file
+fn crate_render_hash(
+  workspace_context: &WorkspaceContext,
+  package: &CrateContext,
+  render_details: &RenderDetails,
+  is_remote_genmode: bool,
+) -> Result<String> {
+  let context = serde_json::to_string(&(
+    workspace_context,
+    package,
+    &render_details.rust_rules_workspace_name,
+    render_details.license_report.is_some(),
+    render_details.render_license_targets,
+    is_remote_genmode,
+  ))?;
+  let additional_build_file = package
+    .canonical_additional_build_file
+    .as_ref()
+    .and_then(|file_path| std::fs::read_to_string(file_path).ok());
+
+  Ok(content_hash(&serde_json::to_string(&(
+    context,
+    TEMPLATES,
+    additional_build_file,
+  ))?))
+}
+
 fn include_additional_build_file(
   package: &CrateContext,
   existing_contents: String,
@@ -357,27 +614,40 @@ impl BuildRenderer for BazelRenderer {
       file_outputs.extend(self.render_aliases(planned_build, render_details, false)?);
     }
 
-    for package in crate_contexts {
-      let rendered_crate_build_file = self
-        .render_crate(
-          workspace_context,
-          package,
-          &render_details.rust_rules_workspace_name,
-        )
-        .map_err(|e| RazeError::Rendering {
-          crate_name_opt: None,
-          message: unwind_tera_error!(e),
-        })?;
+    // Binary dependencies are resolved using a unique lockfile for Cargo Raze, as in remote mode
+    if let Some(lockfile) = &planned_build.lockfile {
+      if crate_contexts.iter().any(|ctx| ctx.is_binary_dependency) {
+        file_outputs.push(FileOutputs {
+          path: path_prefix.as_path().join("Cargo.raze.lock"),
+          contents: lockfile.to_string(),
+        });
+      }
+    }
 
-      let final_crate_build_file =
-        include_additional_build_file(package, rendered_crate_build_file)?;
+    for package in crate_contexts {
+      let build_file_path = path_prefix.as_path().join(&package.expected_build_path);
+      let final_crate_build_file = self.render_crate_build_file(
+        workspace_context,
+        package,
+        render_details,
+        &build_file_path,
+        /*is_remote_genmode=*/ false,
+      )?;
 
       file_outputs.push(FileOutputs {
-        path: path_prefix.as_path().join(&package.expected_build_path),
+        path: build_file_path,
         contents: final_crate_build_file,
       })
     }
 
+    file_outputs.extend(self.render_cache_file(&path_prefix)?);
+    file_outputs.extend(self.render_license_report(
+      &path_prefix,
+      render_details,
+      crate_contexts,
+    )?);
+    file_outputs.extend(self.render_sboms(&path_prefix, render_details, planned_build)?);
+
     file_outputs.sort();
     Ok(file_outputs)
   }
@@ -407,26 +677,29 @@ impl BuildRenderer for BazelRenderer {
     });
 
     for package in crate_contexts {
-      let rendered_crate_build_file = self
-        .render_remote_crate(
-          workspace_context,
-          package,
-          &render_details.rust_rules_workspace_name,
-        )
-        .map_err(|e| RazeError::Rendering {
-          crate_name_opt: Some(package.pkg_name.to_owned()),
-          message: unwind_tera_error!(e),
-        })?;
-
-      let final_crate_build_file =
-        include_additional_build_file(package, rendered_crate_build_file)?;
+      let build_file_path = path_prefix.as_path().join(&package.expected_build_path);
+      let final_crate_build_file = self.render_crate_build_file(
+        workspace_context,
+        package,
+        render_details,
+        &build_file_path,
+        /*is_remote_genmode=*/ true,
+      )?;
 
       file_outputs.push(FileOutputs {
-        path: path_prefix.as_path().join(&package.expected_build_path),
+        path: build_file_path,
         contents: final_crate_build_file,
       })
     }
 
+    file_outputs.extend(self.render_cache_file(&path_prefix)?);
+    file_outputs.extend(self.render_license_report(
+      &path_prefix,
+      render_details,
+      crate_contexts,
+    )?);
+    file_outputs.extend(self.render_sboms(&path_prefix, render_details, planned_build)?);
+
     if render_details.render_package_aliases {
       file_outputs.extend(self.render_aliases(planned_build, render_details, true)?);
     }
@@ -450,6 +723,20 @@ impl BuildRenderer for BazelRenderer {
       contents: rendered_bzl_fetch_file,
     });
 
+    if render_details.render_module_extension {
+      let rendered_module_extension = self
+        .render_module_extension(workspace_context, crate_contexts)
+        .map_err(|e| RazeError::Rendering {
+          crate_name_opt: None,
+          message: unwind_tera_error!(e),
+        })?;
+
+      file_outputs.push(FileOutputs {
+        path: path_prefix.as_path().join("extensions.bzl"),
+        contents: rendered_module_extension,
+      });
+    }
+
     // Optionally write out a unique lockfile for Cargo Raze. This happens in the case
     // where a project has specified binary dependencies.
     if let Some(lockfile) = &planned_build.lockfile {
@@ -484,7 +771,7 @@ mod tests {
     features::Features,
     planning::PlannedBuild,
     rendering::{FileOutputs, RenderDetails},
-    settings::CrateSettings,
+    settings::{CrateSettings, LicenseReportFormat, RegistryAuth, TargetedRustcSettings},
     testing::{basic_lock_contents, utf8_path},
   };
 
@@ -506,6 +793,10 @@ mod tests {
       rust_rules_workspace_name: "rules_rust".to_owned(),
       experimental_api: true,
       render_package_aliases: true,
+      render_module_extension: false,
+      license_report: None,
+      render_license_targets: false,
+      sbom_formats: Vec::new(),
     }
   }
 
@@ -521,6 +812,8 @@ mod tests {
         // This will typically resolve to:
         // `/some/cargo/root/some/crate`
         workspace_members: vec![Utf8PathBuf::from("some/crate")],
+        workspace_member_packages: Vec::new(),
+        custom_platforms: BTreeMap::new(),
       },
       crate_contexts,
       workspace_aliases: aliases,
@@ -543,6 +836,7 @@ mod tests {
       canonical_additional_build_file: CrateSettings::default().additional_build_file,
       default_deps: CrateDependencyContext::default(),
       targeted_deps: Vec::new(),
+      platform_rustc_settings: BTreeMap::new(),
       workspace_member_dependents: Vec::new(),
       workspace_member_dev_dependents: Vec::new(),
       workspace_member_build_dependents: Vec::new(),
@@ -558,6 +852,8 @@ mod tests {
       }],
       build_script_target: None,
       links: None,
+      homepage: None,
+      repository: None,
       source_details: SourceDetails {
         git_data: None,
         download_url: Some(
@@ -565,9 +861,12 @@ mod tests {
             .parse()
             .unwrap(),
         ),
+        registry_auth: None,
       },
       sha256: None,
       lib_target_name: None,
+      license_files: Vec::new(),
+      package_root: Utf8PathBuf::new(),
     }
   }
 
@@ -590,6 +889,7 @@ mod tests {
       expected_build_path: format!("vendor/test-library-1.1.1/{}", buildfile_suffix),
       default_deps: CrateDependencyContext::default(),
       targeted_deps: Vec::new(),
+      platform_rustc_settings: BTreeMap::new(),
       workspace_member_dependents: Vec::new(),
       workspace_member_dev_dependents: Vec::new(),
       workspace_member_build_dependents: Vec::new(),
@@ -605,6 +905,8 @@ mod tests {
       }],
       build_script_target: None,
       links: Some("ssh2".to_owned()),
+      homepage: None,
+      repository: None,
       source_details: SourceDetails {
         git_data: None,
         download_url: Some(
@@ -612,9 +914,12 @@ mod tests {
             .parse()
             .unwrap(),
         ),
+        registry_auth: None,
       },
       sha256: None,
       lib_target_name: Some("test_library".to_owned()),
+      license_files: Vec::new(),
+      package_root: Utf8PathBuf::new(),
     }
   }
 
@@ -647,6 +952,7 @@ mod tests {
         aliased_dependencies: BTreeMap::new(),
       },
       targeted_deps: Vec::new(),
+      platform_rustc_settings: BTreeMap::new(),
       workspace_member_dependents: Vec::new(),
       workspace_member_dev_dependents: Vec::new(),
       workspace_member_build_dependents: Vec::new(),
@@ -662,6 +968,8 @@ mod tests {
       }],
       build_script_target: None,
       links: Some("ssh2".to_owned()),
+      homepage: None,
+      repository: None,
       source_details: SourceDetails {
         git_data: None,
         download_url: Some(
@@ -669,9 +977,12 @@ mod tests {
             .parse()
             .unwrap(),
         ),
+        registry_auth: None,
       },
       sha256: None,
       lib_target_name: Some("test_proc_macro".to_owned()),
+      license_files: Vec::new(),
+      package_root: Utf8PathBuf::new(),
     }
   }
 
@@ -991,6 +1302,8 @@ mod tests {
       gen_workspace_prefix: "raze".to_owned(),
       output_buildfile_suffix: "BUILD.bazel".to_owned(),
       workspace_members: vec![Utf8PathBuf::from("lib_a"), Utf8PathBuf::from("lib_b")],
+      workspace_member_packages: Vec::new(),
+      custom_platforms: BTreeMap::new(),
     };
 
     let file_outputs = BazelRenderer::new()
@@ -1028,4 +1341,514 @@ mod tests {
     )
     .unwrap();
   }
+
+  #[test]
+  fn test_render_module_extension() {
+    let mut render_details = dummy_render_details("BUILD.bazel");
+    render_details.render_module_extension = true;
+
+    let mut planned_build = dummy_planned_build(vec![dummy_library_crate()], vec![]);
+    planned_build.workspace_context.gen_workspace_prefix = "raze".to_owned();
+
+    let file_outputs = BazelRenderer::new()
+      .render_remote_planned_build(&render_details, &planned_build)
+      .unwrap();
+    let extension_contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/extensions.bzl",
+    );
+
+    assert!(extension_contents.contains(
+      "# raze_crates = use_extension(\"//workspace/prefix:extensions.bzl\", \"raze_crates\")"
+    ));
+    assert!(extension_contents.contains("#     \"raze__test_library__1_1_1\",\n"));
+    assert!(extension_contents.contains("def _raze_crates_impl(module_ctx):"));
+    assert!(extension_contents
+      .contains("    http_archive(\n        name = \"raze__test_library__1_1_1\",\n"));
+    assert!(!extension_contents.contains("maybe("));
+    assert!(extension_contents.contains("raze_crates = module_extension("));
+  }
+
+  #[test]
+  fn test_registry_auth_is_rendered_into_http_archive() {
+    let mut library = dummy_library_crate();
+    library.source_details.registry_auth = Some(RegistryAuth {
+      host: "crates.io".to_owned(),
+      auth_pattern: "Bearer <password>".to_owned(),
+      netrc: Some("/etc/raze.netrc".to_owned()),
+    });
+    let planned_build = dummy_planned_build(vec![library, dummy_binary_crate()], vec![]);
+
+    let file_outputs = BazelRenderer::new()
+      .render_remote_planned_build(&dummy_render_details("BUILD.bazel"), &planned_build)
+      .unwrap();
+    let crates_bzl_contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/crates.bzl",
+    );
+
+    assert!(crates_bzl_contents.contains(
+      "        auth_patterns = {\n            \"crates.io\": \"Bearer <password>\",\n        },\n        \
+       netrc = \"/etc/raze.netrc\",\n"
+    ));
+    assert_eq!(crates_bzl_contents.matches("auth_patterns").count(), 1);
+  }
+
+  #[test]
+  fn module_extension_is_not_rendered_by_default() {
+    let planned_build = dummy_planned_build(vec![dummy_library_crate()], vec![]);
+    let file_outputs = BazelRenderer::new()
+      .render_remote_planned_build(&dummy_render_details("BUILD.bazel"), &planned_build)
+      .unwrap();
+
+    assert!(!file_outputs
+      .iter()
+      .any(|output| output.path.ends_with("extensions.bzl")));
+  }
+
+  #[test]
+  fn test_render_cache_reuses_unchanged_crates() {
+    let dir = TempDir::new().unwrap();
+    let mut render_details = dummy_render_details("BUILD.bazel");
+    render_details.bazel_root = utf8_path(dir.as_ref()).to_path_buf();
+    render_details.cargo_root = utf8_path(dir.as_ref()).to_path_buf();
+    let cache_path = render_details
+      .bazel_root
+      .join(&render_details.path_prefix)
+      .join(RENDER_CACHE_NAME);
+
+    let planned_build =
+      dummy_planned_build(vec![dummy_library_crate(), dummy_binary_crate()], vec![]);
+    let mut renderer = BazelRenderer::new().with_render_cache(RenderCache::load(&cache_path));
+    let file_outputs = renderer
+      .render_remote_planned_build(&render_details, &planned_build)
+      .unwrap();
+    assert_eq!(renderer.render_cache().unwrap().regenerated, 2);
+    for output in file_outputs.iter() {
+      fs::create_dir_all(output.path.parent().unwrap()).unwrap();
+      fs::write(&output.path, &output.contents).unwrap();
+    }
+
+    // Only the crate whose context changed is rendered again
+    let mut library = dummy_library_crate();
+    library.features.features.push("feature3".to_owned());
+    let planned_build = dummy_planned_build(vec![library, dummy_binary_crate()], vec![]);
+    let mut renderer = BazelRenderer::new().with_render_cache(RenderCache::load(&cache_path));
+    let cached_outputs = renderer
+      .render_remote_planned_build(&render_details, &planned_build)
+      .unwrap();
+    let render_cache = renderer.render_cache().unwrap();
+    assert_eq!((render_cache.skipped, render_cache.regenerated), (1, 1));
+
+    let uncached_outputs = BazelRenderer::new()
+      .render_remote_planned_build(&render_details, &planned_build)
+      .unwrap();
+    assert_eq!(
+      cached_outputs
+        .into_iter()
+        .filter(|output| !output.path.ends_with(RENDER_CACHE_NAME))
+        .collect::<Vec<FileOutputs>>(),
+      uncached_outputs
+    );
+  }
+
+  #[test]
+  fn custom_platforms_render_their_labels() {
+    let mut library = dummy_library_crate();
+    library.targeted_deps = vec![CrateTargetedDepContext {
+      target: "cfg(target_os = \"linux\")".to_owned(),
+      deps: CrateDependencyContext {
+        dependencies: vec![BuildableDependency {
+          name: "test-dep".to_owned(),
+          version: Version::parse("0.1.0").unwrap(),
+          buildable_target: "@raze__test_dep__0_1_0//:test_dep".to_owned(),
+          is_proc_macro: false,
+        }]
+        .into_iter()
+        .collect(),
+        ..CrateDependencyContext::default()
+      },
+      platform_targets: vec![
+        "riscv64gc-unknown-linux-gnu".to_owned(),
+        "x86_64-unknown-linux-gnu".to_owned(),
+      ],
+    }];
+
+    let mut planned_build = dummy_planned_build(vec![library], vec![]);
+    planned_build.workspace_context.custom_platforms.insert(
+      "riscv64gc-unknown-linux-gnu".to_owned(),
+      "//platforms:riscv64".to_owned(),
+    );
+
+    let file_outputs = BazelRenderer::new()
+      .render_planned_build(&dummy_render_details("BUILD"), &planned_build)
+      .unwrap();
+    let contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+
+    assert!(contents.contains(
+      r#"        (
+            "//platforms:riscv64",
+            "@rules_rust//rust/platform:x86_64-unknown-linux-gnu",
+        ): [
+            "@raze__test_dep__0_1_0//:test_dep",
+        ],"#
+    ));
+  }
+
+  #[test]
+  fn crates_with_gen_tests_get_rust_test_rules() {
+    let mut library = dummy_library_crate();
+    library.raze_settings.gen_tests = true;
+    library.raze_settings.test_additional_deps = vec!["//third_party:test_helper".to_owned()];
+    library.default_deps.dev_dependencies = vec![BuildableDependency {
+      name: "test-dev-dep".to_owned(),
+      version: Version::parse("0.2.0").unwrap(),
+      buildable_target: "@raze__test_dev_dep__0_2_0//:test_dev_dep".to_owned(),
+      is_proc_macro: false,
+    }]
+    .into_iter()
+    .collect();
+    let buildable_dependency = |name: &str| BuildableDependency {
+      name: name.to_owned(),
+      version: Version::parse("0.3.0").unwrap(),
+      buildable_target: format!("@raze__{}__0_3_0//:{}", name, name),
+      is_proc_macro: false,
+    };
+    library.targeted_deps = vec![CrateTargetedDepContext {
+      target: "cfg(windows)".to_owned(),
+      deps: CrateDependencyContext {
+        dependencies: vec![buildable_dependency("windows_dep")]
+          .into_iter()
+          .collect(),
+        dev_dependencies: vec![buildable_dependency("windows_dev_dep")]
+          .into_iter()
+          .collect(),
+        ..CrateDependencyContext::default()
+      },
+      platform_targets: vec!["x86_64-pc-windows-msvc".to_owned()],
+    }];
+    library.targets.extend(vec![
+      BuildableTarget {
+        name: "integration".to_owned(),
+        kind: "test".to_owned(),
+        path: "tests/integration.rs".to_owned(),
+        edition: "2015".to_owned(),
+      },
+      BuildableTarget {
+        name: "demo".to_owned(),
+        kind: "example".to_owned(),
+        path: "examples/demo.rs".to_owned(),
+        edition: "2015".to_owned(),
+      },
+    ]);
+
+    let file_outputs = render_crates_for_test(vec![library], vec![]);
+    let contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+
+    assert!(contents.contains(
+      r#"rust_test(
+    name = "cargo_unit_test_some_library",
+    crate = ":some_library","#
+    ));
+    assert!(contents.contains(r#"name = "cargo_test_integration","#));
+    assert!(contents.contains(r#"crate_root = "tests/integration.rs","#));
+    assert!(contents.contains(r#"crate_root = "examples/demo.rs","#));
+    assert!(contents.contains(r#"":test_library","#));
+
+    // Examples are programs rather than tests
+    assert!(contents.contains(
+      r#"rust_binary(
+    # Prefix test name to disambiguate from (probable) collision with other targets
+    # N.B.: The exact form of this is subject to change.
+    name = "cargo_example_demo","#
+    ));
+
+    assert_eq!(
+      contents
+        .matches(r#""@raze__test_dev_dep__0_2_0//:test_dev_dep","#)
+        .count(),
+      3
+    );
+    assert_eq!(
+      contents.matches(r#""//third_party:test_helper","#).count(),
+      3
+    );
+
+    // Targeted dev dependencies are available to the unit test, integration test and example, but
+    // not to the library itself
+    assert_eq!(
+      contents
+        .matches(r#""@raze__windows_dev_dep__0_3_0//:windows_dev_dep","#)
+        .count(),
+      3
+    );
+    assert_eq!(
+      contents
+        .matches(r#""@raze__windows_dep__0_3_0//:windows_dep","#)
+        .count(),
+      3
+    );
+  }
+
+  #[test]
+  fn test_targets_are_omitted_by_default() {
+    let mut library = dummy_library_crate();
+    library.targets.push(BuildableTarget {
+      name: "integration".to_owned(),
+      kind: "test".to_owned(),
+      path: "tests/integration.rs".to_owned(),
+      edition: "2015".to_owned(),
+    });
+
+    let file_outputs = render_crates_for_test(vec![library], vec![]);
+    let contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+
+    assert!(!contents.contains("rust_test("));
+    assert!(contents.contains(r#"# Unsupported target "integration" with type "test" omitted"#));
+  }
+
+  #[test]
+  fn platform_rustc_settings_render_as_selects() {
+    let mut library = dummy_library_crate();
+    library.raze_settings.additional_flags = vec!["--cfg=everywhere".to_owned()];
+    library
+      .raze_settings
+      .additional_env
+      .insert("PLATFORM".to_owned(), "any".to_owned());
+    library
+      .raze_settings
+      .additional_env
+      .insert("SHARED".to_owned(), "1".to_owned());
+    library.platform_rustc_settings.insert(
+      "x86_64-pc-windows-msvc".to_owned(),
+      TargetedRustcSettings {
+        additional_flags: vec!["--cfg=windows_only".to_owned()],
+        additional_env: vec![("PLATFORM".to_owned(), "windows".to_owned())]
+          .into_iter()
+          .collect(),
+      },
+    );
+
+    let file_outputs = render_crates_for_test(vec![library], vec![]);
+    let contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+
+    assert!(contents.contains(
+      r#"    rustc_env = select({
+        "@rules_rust//rust/platform:x86_64-pc-windows-msvc": {
+            "SHARED": "1",
+            "PLATFORM": "windows",
+        },
+        "//conditions:default": {
+            "PLATFORM": "any",
+            "SHARED": "1",
+        },
+    }),
+    rustc_flags = [
+        "--cap-lints=allow",
+        "--cfg=everywhere",
+    ] + select({
+        "@rules_rust//rust/platform:x86_64-pc-windows-msvc": [
+            "--cfg=windows_only",
+        ],
+        "//conditions:default": [],
+    }),"#
+    ));
+  }
+
+  #[test]
+  fn license_report_collects_license_files() {
+    let sources_dir = TempDir::new().unwrap();
+    let package_root = utf8_path(sources_dir.as_ref());
+    std::fs::write(
+      package_root.join("LICENSE-MIT"),
+      "Permission is hereby granted\n",
+    )
+    .unwrap();
+
+    let mut library = dummy_library_crate();
+    library.license.expression = "MIT".to_owned();
+    library.license_files = vec![Utf8PathBuf::from("LICENSE-MIT")];
+    library.package_root = package_root.to_path_buf();
+
+    let mut render_details = dummy_render_details("BUILD");
+    render_details.license_report = Some(LicenseReportFormat::Markdown);
+    let file_outputs = BazelRenderer::new()
+      .render_planned_build(
+        &render_details,
+        &dummy_planned_build(vec![library, dummy_binary_crate()], vec![]),
+      )
+      .unwrap();
+
+    let report = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/licenses/THIRD_PARTY_LICENSES.md",
+    );
+    assert!(report.contains(
+      r#"## MIT
+
+### test-library 1.1.1
+
+#### LICENSE-MIT
+
+```text
+Permission is hereby granted
+```"#
+    ));
+    assert!(report.contains(
+      r#"## Unspecified
+
+### test-binary 1.1.1
+
+No license files were found for this crate."#
+    ));
+
+    let licenses_build_file = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/licenses/BUILD.bazel",
+    );
+    assert!(licenses_build_file.contains(
+      r#"    srcs = [
+        "@raze__test_library__1_1_1//:license_files",
+    ],"#
+    ));
+
+    let crate_build_file = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+    assert!(crate_build_file.ends_with(
+      r#"filegroup(
+    name = "license_files",
+    srcs = [
+        "LICENSE-MIT",
+    ],
+)
+"#
+    ));
+  }
+
+  #[test]
+  fn license_texts_cannot_escape_their_code_fence() {
+    let sources_dir = TempDir::new().unwrap();
+    let package_root = utf8_path(sources_dir.as_ref());
+    std::fs::write(package_root.join("NOTICE"), "```\nNot code\n````\n").unwrap();
+
+    let mut library = dummy_library_crate();
+    library.license_files = vec![Utf8PathBuf::from("NOTICE")];
+    library.package_root = package_root.to_path_buf();
+
+    let mut render_details = dummy_render_details("BUILD");
+    render_details.license_report = Some(LicenseReportFormat::Markdown);
+    let file_outputs = BazelRenderer::new()
+      .render_planned_build(&render_details, &dummy_planned_build(vec![library], vec![]))
+      .unwrap();
+
+    let report = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/licenses/THIRD_PARTY_LICENSES.md",
+    );
+    assert!(report.contains(
+      r#"#### NOTICE
+
+`````text
+```
+Not code
+````
+`````"#
+    ));
+  }
+
+  #[test]
+  fn license_files_are_not_rendered_by_default() {
+    let mut library = dummy_library_crate();
+    library.license_files = vec![Utf8PathBuf::from("LICENSE-MIT")];
+
+    let file_outputs = render_crates_for_test(vec![library], vec![]);
+    assert!(!file_outputs
+      .iter()
+      .any(|output| output.path.as_str().contains("/licenses/")));
+    assert!(!file_outputs
+      .iter()
+      .any(|output| output.contents.contains("license_files")));
+  }
+
+  #[test]
+  fn license_targets_are_rendered_for_crates() {
+    let mut library = dummy_library_crate();
+    library.license = LicenseData {
+      name: "MIT from expression \"MIT OR Apache-2.0\"".to_owned(),
+      rating: "notice".to_owned(),
+      expression: "MIT OR Apache-2.0".to_owned(),
+      spdx_ids: vec!["Apache-2.0".to_owned(), "MIT".to_owned()],
+    };
+    library.license_files = vec![Utf8PathBuf::from("LICENSE-MIT")];
+
+    let mut render_details = dummy_render_details("BUILD");
+    render_details.render_license_targets = true;
+    let file_outputs = BazelRenderer::new()
+      .render_planned_build(&render_details, &dummy_planned_build(vec![library], vec![]))
+      .unwrap();
+    let contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+
+    assert!(contents.contains(
+      r#"    default_package_metadata = [
+        ":license",
+        ":package_info",
+    ],"#
+    ));
+    assert!(contents.contains(
+      r#"license(
+    name = "license",
+    license_kinds = [
+        "@rules_license//licenses/spdx:Apache-2.0",
+        "@rules_license//licenses/spdx:MIT",
+    ],
+    license_text = "LICENSE-MIT",
+    package_name = "test-library",
+    package_url = "pkg:cargo/test-library@1.1.1",
+    package_version = "1.1.1",
+)"#
+    ));
+    assert!(contents.contains(r#"    name = "package_info","#));
+    assert!(!contents.contains("/download"));
+
+    // The repository declared by the crate is preferred over its homepage
+    let mut library = dummy_library_crate();
+    library.homepage = Some("https://example.com/test-library".to_owned());
+    library.repository = Some("https://github.com/example/test-library".to_owned());
+    let file_outputs = BazelRenderer::new()
+      .render_planned_build(&render_details, &dummy_planned_build(vec![library], vec![]))
+      .unwrap();
+    let contents = extract_contents_matching_path(
+      &file_outputs,
+      "/some/bazel/root/./some_render_prefix/vendor/test-library-1.1.1/BUILD",
+    );
+    assert_eq!(
+      contents
+        .matches(r#"    package_url = "https://github.com/example/test-library","#)
+        .count(),
+      2
+    );
+
+    // Nothing from rules_license is rendered by default
+    let file_outputs = render_crates_for_test(vec![dummy_library_crate()], vec![]);
+    assert!(!file_outputs
+      .iter()
+      .any(|output| output.contents.contains("rules_license")));
+  }
 }
@@ -0,0 +1,251 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, fs};
+
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use rustc_serialize::hex::ToHex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::rendering::FileOutputs;
+
+/// The name of the file which records the content hashes of rendered crates
+pub const RENDER_CACHE_NAME: &str = ".cargo-raze-cache";
+
+/// The hashes recorded for a single BUILD file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+  /// The hash of the inputs the BUILD file was rendered from
+  inputs: String,
+  /// The hash of the rendered BUILD file
+  output: String,
+}
+
+/// Records a content hash of the inputs used to render each crate's BUILD file, so crates whose
+/// inputs are unchanged can be skipped on subsequent runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RenderCache {
+  /// The version of cargo-raze which wrote the cache
+  raze_version: String,
+  /// A mapping of BUILD file paths, relative to the cache file, to their hashes
+  entries: BTreeMap<Utf8PathBuf, CacheEntry>,
+
+  /// The hashes recorded by a previous run
+  #[serde(skip)]
+  previous_entries: BTreeMap<Utf8PathBuf, CacheEntry>,
+  /// The number of crates whose existing BUILD file was reused
+  #[serde(skip)]
+  pub skipped: usize,
+  /// The number of crates which were rendered
+  #[serde(skip)]
+  pub regenerated: usize,
+}
+
+impl RenderCache {
+  /// Creates a cache with no previous entries. All crates will be rendered.
+  pub fn new() -> Self {
+    RenderCache {
+      raze_version: env!("CARGO_PKG_VERSION").to_owned(),
+      ..RenderCache::default()
+    }
+  }
+
+  /// Loads the cache written by a previous run. A missing or unreadable cache is treated as empty.
+  pub fn load(path: &Utf8Path) -> Self {
+    let mut cache = RenderCache::new();
+    let previous = fs::read_to_string(path)
+      .ok()
+      .and_then(|contents| serde_json::from_str::<RenderCache>(&contents).ok())
+      .filter(|previous| previous.raze_version == cache.raze_version);
+
+    if let Some(previous) = previous {
+      let root = path.parent().unwrap_or(path);
+      cache.previous_entries = previous
+        .entries
+        .into_iter()
+        .map(|(build_file, entry)| (root.join(build_file), entry))
+        .collect();
+    }
+    cache
+  }
+
+  /// Returns the contents of the existing BUILD file if its inputs match those of the last run
+  /// and it has not been modified since it was rendered.
+  ///
+  /// A reused BUILD file is recorded for the next run. Otherwise the file must be rendered and
+  /// passed to [RenderCache::record].
+  pub fn lookup(&mut self, build_file: &Utf8Path, inputs_hash: &str) -> Option<String> {
+    let contents = self
+      .previous_entries
+      .get(build_file)
+      .filter(|entry| entry.inputs == inputs_hash)
+      .and_then(|entry| {
+        fs::read_to_string(build_file)
+          .ok()
+          .filter(|contents| content_hash(contents) == entry.output)
+      });
+
+    match &contents {
+      Some(contents) => {
+        self.skipped += 1;
+        self.record(build_file, inputs_hash, contents);
+      }
+      None => self.regenerated += 1,
+    }
+    contents
+  }
+
+  /// Records the hashes of a rendered BUILD file, so it can be saved for the next run.
+  pub fn record(&mut self, build_file: &Utf8Path, inputs_hash: &str, contents: &str) {
+    self.entries.insert(
+      build_file.to_path_buf(),
+      CacheEntry {
+        inputs: inputs_hash.to_owned(),
+        output: content_hash(contents),
+      },
+    );
+  }
+
+  /// Renders the cache to be written to `path`. Entries are stored relative to the cache file.
+  pub fn render(&self, path: &Utf8Path) -> Result<FileOutputs> {
+    let root = path.parent().unwrap_or(path);
+    let cache = RenderCache {
+      raze_version: self.raze_version.clone(),
+      entries: self
+        .entries
+        .iter()
+        .map(|(build_file, entry)| {
+          (
+            build_file
+              .strip_prefix(root)
+              .unwrap_or(build_file)
+              .to_path_buf(),
+            entry.clone(),
+          )
+        })
+        .collect(),
+      ..RenderCache::default()
+    };
+
+    let mut contents = serde_json::to_string_pretty(&cache)?;
+    contents.push('\n');
+    Ok(FileOutputs {
+      path: path.to_path_buf(),
+      contents,
+    })
+  }
+}
+
+/// Produces a hex encoded sha256 hash of the given contents, which is stable across toolchains.
+pub fn content_hash<T: AsRef<[u8]> + ?Sized>(contents: &T) -> String {
+  Sha256::digest(contents.as_ref()).to_hex()
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::TempDir;
+
+  use crate::testing::utf8_path;
+
+  use super::*;
+
+  #[test]
+  fn test_render_cache_skips_unchanged_crates() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref());
+    let cache_path = root.join(RENDER_CACHE_NAME);
+
+    let unchanged = root.join("remote/BUILD.unchanged-1.0.0.bazel");
+    let changed = root.join("remote/BUILD.changed-1.0.0.bazel");
+    fs::create_dir_all(unchanged.parent().unwrap()).unwrap();
+
+    // Nothing is reused on the first run
+    let mut cache = RenderCache::load(&cache_path);
+    assert_eq!(cache.lookup(&unchanged, "hash_a"), None);
+    assert_eq!(cache.lookup(&changed, "hash_b"), None);
+    assert_eq!((cache.skipped, cache.regenerated), (0, 2));
+    for (build_file, hash, contents) in [
+      (&unchanged, "hash_a", "unchanged contents"),
+      (&changed, "hash_b", "changed contents"),
+    ] {
+      cache.record(build_file, hash, contents);
+      fs::write(build_file, contents).unwrap();
+    }
+
+    let output = cache.render(&cache_path).unwrap();
+    assert!(output.contents.contains(&format!(
+      "\"remote/BUILD.unchanged-1.0.0.bazel\": {{\n      \"inputs\": \"hash_a\",\n      \"output\": \"{}\"",
+      content_hash("unchanged contents")
+    )));
+    fs::write(&output.path, &output.contents).unwrap();
+
+    let mut cache = RenderCache::load(&cache_path);
+    assert_eq!(
+      cache.lookup(&unchanged, "hash_a"),
+      Some("unchanged contents".to_owned())
+    );
+    assert_eq!(cache.lookup(&changed, "hash_c"), None);
+    assert_eq!((cache.skipped, cache.regenerated), (1, 1));
+  }
+
+  #[test]
+  fn test_render_cache_regenerates_modified_build_files() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref());
+    let cache_path = root.join(RENDER_CACHE_NAME);
+    let build_file = root.join("BUILD.edited-1.0.0.bazel");
+
+    let mut cache = RenderCache::new();
+    cache.record(&build_file, "hash", "rendered contents");
+    let output = cache.render(&cache_path).unwrap();
+    fs::write(&output.path, &output.contents).unwrap();
+
+    // A hand edited BUILD file is rendered again even though its inputs are unchanged
+    fs::write(&build_file, "edited contents").unwrap();
+    let mut cache = RenderCache::load(&cache_path);
+    assert_eq!(cache.lookup(&build_file, "hash"), None);
+    assert_eq!(cache.regenerated, 1);
+  }
+
+  #[test]
+  fn test_render_cache_ignores_missing_build_files() {
+    let dir = TempDir::new().unwrap();
+    let root = utf8_path(dir.as_ref());
+    let cache_path = root.join(RENDER_CACHE_NAME);
+    let build_file = root.join("remote/BUILD.deleted-1.0.0.bazel");
+
+    let mut cache = RenderCache::new();
+    cache.record(&build_file, "hash", "contents");
+    let output = cache.render(&cache_path).unwrap();
+    fs::write(&output.path, &output.contents).unwrap();
+
+    let mut cache = RenderCache::load(&cache_path);
+    assert_eq!(cache.lookup(&build_file, "hash"), None);
+    assert_eq!(cache.regenerated, 1);
+  }
+
+  #[test]
+  fn test_content_hash_is_sha256() {
+    assert_eq!(
+      content_hash("cargo-raze"),
+      content_hash(&"cargo-raze".to_owned())
+    );
+    assert_eq!(
+      content_hash(""),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+  }
+}
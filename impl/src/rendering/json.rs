@@ -0,0 +1,241 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+  context::{CrateContext, DependencyAlias, WorkspaceContext},
+  planning::PlannedBuild,
+  rendering::{BuildRenderer, FileOutputs, RenderDetails},
+  settings::GenMode,
+};
+
+/// The version of the exported JSON schema. This is incremented whenever a change is made that
+/// could break existing consumers, such as removing or renaming a field.
+pub const PLANNED_BUILD_SCHEMA_VERSION: u32 = 1;
+
+/// The name of the file the planned build is exported to, relative to the workspace path
+pub const PLANNED_BUILD_FILE_NAME: &str = "planned_build.json";
+
+/// A serializable view of a `PlannedBuild` for consumption by external tools.
+#[derive(Debug, Serialize)]
+pub struct PlannedBuildExport<'planned_build> {
+  /// The version of this schema. See `PLANNED_BUILD_SCHEMA_VERSION`
+  pub schema_version: u32,
+  /// The version of cargo-raze which produced the export
+  pub raze_version: &'static str,
+  /// The mode the build was planned for
+  pub genmode: GenMode,
+  /// The overall context for this workspace
+  pub workspace_context: &'planned_build WorkspaceContext,
+  /// Aliases that are defined at the workspace root
+  pub workspace_aliases: &'planned_build [DependencyAlias],
+  /// Every crate in the build, including its dependencies, features, license and source
+  pub crates: &'planned_build [CrateContext],
+}
+
+impl<'planned_build> PlannedBuildExport<'planned_build> {
+  pub fn new(planned_build: &'planned_build PlannedBuild, genmode: GenMode) -> Self {
+    PlannedBuildExport {
+      schema_version: PLANNED_BUILD_SCHEMA_VERSION,
+      raze_version: env!("CARGO_PKG_VERSION"),
+      genmode,
+      workspace_context: &planned_build.workspace_context,
+      workspace_aliases: &planned_build.workspace_aliases,
+      crates: &planned_build.crate_contexts,
+    }
+  }
+}
+
+/// Renders a `PlannedBuild` as versioned JSON in place of Bazel BUILD files.
+#[derive(Default)]
+pub struct JsonRenderer {}
+
+impl JsonRenderer {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn render_export(
+    &self,
+    render_details: &RenderDetails,
+    planned_build: &PlannedBuild,
+    genmode: GenMode,
+  ) -> Result<Vec<FileOutputs>> {
+    let export = PlannedBuildExport::new(planned_build, genmode);
+    let mut contents = serde_json::to_string_pretty(&export)?;
+    contents.push('\n');
+
+    Ok(vec![FileOutputs {
+      path: render_details
+        .bazel_root
+        .join(&render_details.path_prefix)
+        .join(PLANNED_BUILD_FILE_NAME),
+      contents,
+    }])
+  }
+}
+
+impl BuildRenderer for JsonRenderer {
+  fn render_planned_build(
+    &mut self,
+    render_details: &RenderDetails,
+    planned_build: &PlannedBuild,
+  ) -> Result<Vec<FileOutputs>> {
+    self.render_export(render_details, planned_build, GenMode::Vendored)
+  }
+
+  fn render_remote_planned_build(
+    &mut self,
+    render_details: &RenderDetails,
+    planned_build: &PlannedBuild,
+  ) -> Result<Vec<FileOutputs>> {
+    self.render_export(render_details, planned_build, GenMode::Remote)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+
+  use camino::Utf8PathBuf;
+  use semver::Version;
+  use serde_json::Value;
+
+  use crate::{context::*, features::Features, settings::CrateSettings};
+
+  use super::*;
+
+  fn dummy_render_details() -> RenderDetails {
+    RenderDetails {
+      cargo_root: Utf8PathBuf::from("/some/cargo/root"),
+      path_prefix: Utf8PathBuf::from("cargo"),
+      package_aliases_dir: "cargo".to_string(),
+      vendored_buildfile_name: "BUILD.bazel".to_owned(),
+      bazel_root: Utf8PathBuf::from("/some/bazel/root"),
+      rust_rules_workspace_name: "rules_rust".to_owned(),
+      experimental_api: true,
+      render_package_aliases: true,
+      render_module_extension: false,
+      license_report: None,
+      render_license_targets: false,
+      sbom_formats: Vec::new(),
+    }
+  }
+
+  fn dummy_library_crate() -> CrateContext {
+    CrateContext {
+      pkg_name: "test-library".to_owned(),
+      pkg_version: Version::parse("1.1.1").unwrap(),
+      edition: "2015".to_owned(),
+      features: Features {
+        features: vec!["std".to_owned()],
+        targeted_features: vec![],
+      },
+      expected_build_path: "remote/BUILD.test-library-1.1.1.bazel".to_owned(),
+      license: LicenseData {
+        name: "MIT".to_owned(),
+        rating: "notice".to_owned(),
+        expression: "MIT".to_owned(),
+        spdx_ids: vec!["MIT".to_owned()],
+      },
+      raze_settings: CrateSettings::default(),
+      canonical_additional_build_file: None,
+      default_deps: CrateDependencyContext {
+        dependencies: vec![BuildableDependency {
+          name: "test-dep".to_owned(),
+          version: Version::parse("0.1.0").unwrap(),
+          buildable_target: "@raze__test_dep__0_1_0//:test_dep".to_owned(),
+          is_proc_macro: false,
+        }]
+        .into_iter()
+        .collect(),
+        ..CrateDependencyContext::default()
+      },
+      targeted_deps: Vec::new(),
+      platform_rustc_settings: BTreeMap::new(),
+      workspace_member_dependents: Vec::new(),
+      workspace_member_dev_dependents: Vec::new(),
+      workspace_member_build_dependents: Vec::new(),
+      is_workspace_member_dependency: true,
+      is_binary_dependency: false,
+      is_proc_macro: false,
+      workspace_path_to_crate: "@raze__test_library__1_1_1//".to_owned(),
+      targets: Vec::new(),
+      build_script_target: None,
+      links: None,
+      homepage: None,
+      repository: None,
+      source_details: SourceDetails {
+        git_data: None,
+        download_url: Some(
+          "https://crates.io/api/v1/crates/test-library/1.1.1/download"
+            .parse()
+            .unwrap(),
+        ),
+        registry_auth: None,
+      },
+      sha256: Some("abc123".to_owned()),
+      lib_target_name: Some("test_library".to_owned()),
+      license_files: vec![Utf8PathBuf::from("LICENSE")],
+      package_root: Utf8PathBuf::from("/some/registry/test-library-1.1.1"),
+    }
+  }
+
+  #[test]
+  fn test_render_planned_build_as_json() {
+    let planned_build = PlannedBuild {
+      workspace_context: WorkspaceContext {
+        workspace_path: "//cargo".to_owned(),
+        gen_workspace_prefix: "raze".to_owned(),
+        output_buildfile_suffix: "BUILD.bazel".to_owned(),
+        workspace_members: vec![Utf8PathBuf::from("some/crate")],
+        workspace_member_packages: Vec::new(),
+        custom_platforms: BTreeMap::new(),
+      },
+      crate_contexts: vec![dummy_library_crate()],
+      workspace_aliases: Vec::new(),
+      lockfile: None,
+    };
+
+    let file_outputs = JsonRenderer::new()
+      .render_remote_planned_build(&dummy_render_details(), &planned_build)
+      .unwrap();
+    assert_eq!(file_outputs.len(), 1);
+    assert_eq!(
+      file_outputs[0].path,
+      Utf8PathBuf::from("/some/bazel/root/cargo/planned_build.json")
+    );
+
+    let json: Value = serde_json::from_str(&file_outputs[0].contents).unwrap();
+    assert_eq!(json["schema_version"], PLANNED_BUILD_SCHEMA_VERSION);
+    assert_eq!(json["genmode"], "Remote");
+    assert_eq!(json["workspace_context"]["workspace_path"], "//cargo");
+
+    let library = &json["crates"][0];
+    assert_eq!(library["pkg_name"], "test-library");
+    assert_eq!(library["pkg_version"], "1.1.1");
+    assert_eq!(library["license"]["name"], "MIT");
+    assert_eq!(library["features"]["features"][0], "std");
+    assert_eq!(
+      library["default_deps"]["dependencies"][0]["buildable_target"],
+      "@raze__test_dep__0_1_0//:test_dep"
+    );
+    assert_eq!(
+      library["source_details"]["download_url"],
+      "https://crates.io/api/v1/crates/test-library/1.1.1/download"
+    );
+  }
+}
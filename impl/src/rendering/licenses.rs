@@ -0,0 +1,129 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs,
+};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use serde::Serialize;
+use tera::{to_value, try_get_value, Value};
+
+use crate::{context::CrateContext, settings::LicenseReportFormat};
+
+/// The version of the JSON license report schema. This is incremented whenever a change is made
+/// that could break existing consumers, such as removing or renaming a field.
+pub const LICENSE_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// The name of the Bazel package the license report is rendered into, relative to the workspace path
+pub const LICENSES_PACKAGE_NAME: &str = "licenses";
+
+/// The group crates which declare no license expression are listed under
+const UNSPECIFIED_LICENSE_EXPRESSION: &str = "Unspecified";
+
+/// A Tera filter returning a Markdown code fence for the given text: a run of backticks longer
+/// than any within the text, so the text can never close the fence early.
+pub fn code_fence_filter(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+  let text = try_get_value!("code_fence", "value", String, value);
+  let longest_backtick_run = text
+    .split(|c| c != '`')
+    .map(str::len)
+    .max()
+    .unwrap_or_default();
+  Ok(to_value(
+    "`".repeat(std::cmp::max(3, longest_backtick_run + 1)),
+  )?)
+}
+
+/// Returns the name of the license report file for the given format
+pub fn license_report_file_name(format: LicenseReportFormat) -> &'static str {
+  match format {
+    LicenseReportFormat::Markdown => "THIRD_PARTY_LICENSES.md",
+    LicenseReportFormat::Html => "THIRD_PARTY_LICENSES.html",
+    LicenseReportFormat::Json => "THIRD_PARTY_LICENSES.json",
+  }
+}
+
+/// The licenses of every crate in a build, grouped by their license expression.
+#[derive(Debug, Serialize)]
+pub struct LicenseReport<'planned_build> {
+  /// The version of this schema. See `LICENSE_REPORT_SCHEMA_VERSION`
+  pub schema_version: u32,
+  /// Each distinct license expression, in sorted order
+  pub licenses: Vec<LicenseGroup<'planned_build>>,
+}
+
+/// The crates which share a license expression
+#[derive(Debug, Serialize)]
+pub struct LicenseGroup<'planned_build> {
+  pub expression: &'planned_build str,
+  pub crates: Vec<LicensedCrate<'planned_build>>,
+}
+
+/// A crate and the contents of its license files
+#[derive(Debug, Serialize)]
+pub struct LicensedCrate<'planned_build> {
+  pub name: &'planned_build str,
+  pub version: String,
+  pub license_files: Vec<LicenseText>,
+}
+
+/// The contents of a license file, along with its path relative to the crate's root
+#[derive(Debug, Serialize)]
+pub struct LicenseText {
+  pub path: Utf8PathBuf,
+  pub text: String,
+}
+
+impl<'planned_build> LicenseReport<'planned_build> {
+  /// Collects the license files of the given crates from their sources.
+  pub fn new(crate_contexts: &'planned_build [CrateContext]) -> Result<Self> {
+    let mut groups: BTreeMap<&str, Vec<LicensedCrate>> = BTreeMap::new();
+    for package in crate_contexts {
+      let license_files = package
+        .license_files
+        .iter()
+        .map(|license_file| {
+          let file_path = package.package_root.join(license_file);
+          let text = fs::read_to_string(&file_path)
+            .with_context(|| format!("Failed to read license file: {}", file_path))?;
+          Ok(LicenseText {
+            path: license_file.clone(),
+            text,
+          })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+      let expression = match package.license.expression.as_str() {
+        "" => UNSPECIFIED_LICENSE_EXPRESSION,
+        expression => expression,
+      };
+      groups.entry(expression).or_default().push(LicensedCrate {
+        name: &package.pkg_name,
+        version: package.pkg_version.to_string(),
+        license_files,
+      });
+    }
+
+    Ok(LicenseReport {
+      schema_version: LICENSE_REPORT_SCHEMA_VERSION,
+      licenses: groups
+        .into_iter()
+        .map(|(expression, crates)| LicenseGroup { expression, crates })
+        .collect(),
+    })
+  }
+}
@@ -0,0 +1,723 @@
+// Copyright 2022 Google Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  iter,
+};
+
+use anyhow::Result;
+use camino::Utf8Path;
+use semver::Version;
+use serde_json::{json, Value};
+
+use crate::{
+  context::{BuildableDependency, CrateContext, WorkspaceMemberPackage},
+  planning::PlannedBuild,
+  rendering::{cache::content_hash, FileOutputs},
+  settings::SbomFormat,
+};
+
+/// The name of the Bazel package SBOMs are rendered into, relative to the workspace path
+pub const SBOM_PACKAGE_NAME: &str = "sbom";
+
+/// SPDX documents require a creation time. A fixed one keeps the documents reproducible, so they
+/// only change when the dependency graph does.
+const SPDX_CREATION_TIME: &str = "1970-01-01T00:00:00Z";
+
+/// The number of hex digits of the hash suffixed to escaped SPDX element ids
+const SPDX_ID_HASH_LENGTH: usize = 8;
+
+/// Returns the name of the SBOM file of a workspace member for the given format
+pub fn sbom_file_name(member_name: &str, format: SbomFormat) -> String {
+  match format {
+    SbomFormat::CycloneDx => format!("{}.cdx.json", member_name),
+    SbomFormat::Spdx => format!("{}.spdx.json", member_name),
+  }
+}
+
+/// Renders a document in each of the given formats for every workspace member into `sbom_dir`.
+pub fn render_sboms(
+  planned_build: &PlannedBuild,
+  formats: &[SbomFormat],
+  sbom_dir: &Utf8Path,
+) -> Result<Vec<FileOutputs>> {
+  let mut file_outputs = Vec::new();
+  for member in planned_build
+    .workspace_context
+    .workspace_member_packages
+    .iter()
+  {
+    let sbom = Sbom::for_member(member, planned_build);
+    for format in formats.iter().copied() {
+      let document = match format {
+        SbomFormat::CycloneDx => sbom.to_cyclonedx(),
+        SbomFormat::Spdx => sbom.to_spdx(),
+      };
+      let mut contents = serde_json::to_string_pretty(&document)?;
+      contents.push('\n');
+
+      file_outputs.push(FileOutputs {
+        path: sbom_dir.join(sbom_file_name(&member.name, format)),
+        contents,
+      });
+    }
+  }
+
+  Ok(file_outputs)
+}
+
+/// A package's name and version, which uniquely identify it within a build
+type PackageKey<'planned_build> = (&'planned_build str, &'planned_build Version);
+
+/// A package described by a software bill of materials
+#[derive(Debug)]
+struct SbomPackage<'planned_build> {
+  name: &'planned_build str,
+  version: &'planned_build Version,
+  // The crate this package was planned from, or None for workspace members
+  crate_context: Option<&'planned_build CrateContext>,
+  dependencies: BTreeSet<PackageKey<'planned_build>>,
+}
+
+/// A workspace member and every package it transitively depends on
+#[derive(Debug)]
+pub struct Sbom<'planned_build> {
+  root: SbomPackage<'planned_build>,
+  // Sorted by name and version
+  packages: Vec<SbomPackage<'planned_build>>,
+}
+
+impl<'planned_build> Sbom<'planned_build> {
+  /// Collects the transitive closure of a workspace member's normal and build dependencies.
+  ///
+  /// Dependencies of every platform are included, as are other workspace members the member
+  /// depends on.
+  pub fn for_member(
+    member: &'planned_build WorkspaceMemberPackage,
+    planned_build: &'planned_build PlannedBuild,
+  ) -> Self {
+    let find_member = |path| {
+      planned_build
+        .workspace_context
+        .workspace_member_packages
+        .iter()
+        .find(|member| &member.path == path)
+    };
+    let find_crate = |dep: &BuildableDependency| {
+      planned_build
+        .crate_contexts
+        .iter()
+        .find(|package| package.pkg_name == dep.name && package.pkg_version == dep.version)
+    };
+
+    let mut packages: BTreeMap<PackageKey, SbomPackage> = BTreeMap::new();
+
+    let mut member_queue = vec![member];
+    let mut crate_queue = Vec::new();
+    while let Some(current) = member_queue.pop() {
+      if packages.contains_key(&(current.name.as_str(), &current.version)) {
+        continue;
+      }
+
+      let member_dependencies: Vec<&WorkspaceMemberPackage> = current
+        .workspace_member_dependencies
+        .iter()
+        .filter_map(find_member)
+        .collect();
+      let crate_dependencies: Vec<&CrateContext> = planned_build
+        .crate_contexts
+        .iter()
+        .filter(|package| {
+          package.workspace_member_dependents.contains(&current.path)
+            || package
+              .workspace_member_build_dependents
+              .contains(&current.path)
+        })
+        .collect();
+
+      let dependencies = member_dependencies
+        .iter()
+        .map(|dep| (dep.name.as_str(), &dep.version))
+        .chain(
+          crate_dependencies
+            .iter()
+            .map(|dep| (dep.pkg_name.as_str(), &dep.pkg_version)),
+        )
+        .collect();
+      packages.insert(
+        (current.name.as_str(), &current.version),
+        SbomPackage {
+          name: &current.name,
+          version: &current.version,
+          crate_context: None,
+          dependencies,
+        },
+      );
+
+      member_queue.extend(member_dependencies);
+      crate_queue.extend(crate_dependencies);
+    }
+
+    while let Some(current) = crate_queue.pop() {
+      if packages.contains_key(&(current.pkg_name.as_str(), &current.pkg_version)) {
+        continue;
+      }
+
+      let crate_dependencies: Vec<&CrateContext> = iter::once(&current.default_deps)
+        .chain(current.targeted_deps.iter().map(|targeted| &targeted.deps))
+        .flat_map(|deps| {
+          deps
+            .dependencies
+            .iter()
+            .chain(deps.proc_macro_dependencies.iter())
+            .chain(deps.build_dependencies.iter())
+            .chain(deps.build_proc_macro_dependencies.iter())
+        })
+        .filter_map(find_crate)
+        .collect();
+
+      packages.insert(
+        (current.pkg_name.as_str(), &current.pkg_version),
+        SbomPackage {
+          name: &current.pkg_name,
+          version: &current.pkg_version,
+          crate_context: Some(current),
+          dependencies: crate_dependencies
+            .iter()
+            .map(|dep| (dep.pkg_name.as_str(), &dep.pkg_version))
+            .collect(),
+        },
+      );
+
+      crate_queue.extend(crate_dependencies);
+    }
+
+    // UNWRAP: The member was the first package to be inserted
+    let root = packages
+      .remove(&(member.name.as_str(), &member.version))
+      .unwrap();
+
+    Sbom {
+      root,
+      packages: packages.into_values().collect(),
+    }
+  }
+
+  /// Produces a CycloneDX 1.4 JSON document
+  pub fn to_cyclonedx(&self) -> Value {
+    let component = |package: &SbomPackage, component_type: &str| {
+      let mut component = json!({
+        "type": component_type,
+        "bom-ref": purl(package.name, package.version),
+        "name": package.name,
+        "version": package.version.to_string(),
+        "purl": purl(package.name, package.version),
+      });
+
+      if let Some(sha256) = package.crate_context.and_then(|ctx| ctx.sha256.as_ref()) {
+        component["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+      }
+      if let Some(expression) = package.license_expression() {
+        component["licenses"] = json!([{ "expression": expression }]);
+      }
+
+      let source_details = package.crate_context.map(|ctx| &ctx.source_details);
+      if let Some(git_data) = source_details.and_then(|details| details.git_data.as_ref()) {
+        component["externalReferences"] = json!([{
+          "type": "vcs",
+          "url": git_data.remote,
+          "comment": format!("commit {}", git_data.commit),
+        }]);
+      } else if let Some(url) = source_details.and_then(|details| details.download_url.as_ref()) {
+        component["externalReferences"] = json!([{ "type": "distribution", "url": url }]);
+      }
+
+      component
+    };
+
+    let dependencies: Vec<Value> = iter::once(&self.root)
+      .chain(self.packages.iter())
+      .map(|package| {
+        json!({
+          "ref": purl(package.name, package.version),
+          "dependsOn": package
+            .dependencies
+            .iter()
+            .map(|(name, version)| purl(name, version))
+            .collect::<Vec<_>>(),
+        })
+      })
+      .collect();
+
+    json!({
+      "bomFormat": "CycloneDX",
+      "specVersion": "1.4",
+      "version": 1,
+      "metadata": {
+        "tools": [{
+          "vendor": "Google",
+          "name": "cargo-raze",
+          "version": env!("CARGO_PKG_VERSION"),
+        }],
+        "component": component(&self.root, "application"),
+      },
+      "components": self
+        .packages
+        .iter()
+        .map(|package| component(package, "library"))
+        .collect::<Vec<_>>(),
+      "dependencies": dependencies,
+    })
+  }
+
+  /// Produces an SPDX 2.3 JSON document
+  pub fn to_spdx(&self) -> Value {
+    let packages: Vec<Value> = iter::once(&self.root)
+      .chain(self.packages.iter())
+      .map(|package| {
+        let mut spdx_package = json!({
+          "SPDXID": spdx_id(package.name, package.version),
+          "name": package.name,
+          "versionInfo": package.version.to_string(),
+          "downloadLocation": package.spdx_download_location(),
+          "filesAnalyzed": false,
+          "licenseConcluded": "NOASSERTION",
+          "licenseDeclared": package.license_expression().unwrap_or("NOASSERTION"),
+          "copyrightText": "NOASSERTION",
+          "externalRefs": [{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl(package.name, package.version),
+          }],
+        });
+
+        if let Some(sha256) = package.crate_context.and_then(|ctx| ctx.sha256.as_ref()) {
+          spdx_package["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+        }
+
+        spdx_package
+      })
+      .collect();
+
+    let relationships: Vec<Value> = iter::once(json!({
+      "spdxElementId": "SPDXRef-DOCUMENT",
+      "relationshipType": "DESCRIBES",
+      "relatedSpdxElement": spdx_id(self.root.name, self.root.version),
+    }))
+    .chain(
+      iter::once(&self.root)
+        .chain(self.packages.iter())
+        .flat_map(|package| {
+          package.dependencies.iter().map(move |(name, version)| {
+            json!({
+              "spdxElementId": spdx_id(package.name, package.version),
+              "relationshipType": "DEPENDS_ON",
+              "relatedSpdxElement": spdx_id(name, version),
+            })
+          })
+        }),
+    )
+    .collect();
+
+    // The namespace identifies this revision of the document, so it is derived from its contents
+    let namespace_hash = content_hash(&json!([&packages, &relationships]).to_string());
+
+    json!({
+      "spdxVersion": "SPDX-2.3",
+      "dataLicense": "CC0-1.0",
+      "SPDXID": "SPDXRef-DOCUMENT",
+      "name": format!("{}-{}", self.root.name, self.root.version),
+      "documentNamespace": format!(
+        "https://github.com/google/cargo-raze/spdx/{}-{}-{}",
+        self.root.name, self.root.version, namespace_hash
+      ),
+      "creationInfo": {
+        "created": SPDX_CREATION_TIME,
+        "creators": [format!("Tool: cargo-raze-{}", env!("CARGO_PKG_VERSION"))],
+      },
+      "packages": packages,
+      "relationships": relationships,
+    })
+  }
+}
+
+impl<'planned_build> SbomPackage<'planned_build> {
+  /// The package's license expression, if it is valid SPDX
+  fn license_expression(&self) -> Option<&str> {
+    self
+      .crate_context
+      .map(|ctx| &ctx.license)
+      .filter(|license| !license.spdx_ids.is_empty())
+      .map(|license| license.expression.as_str())
+  }
+
+  /// The location of the package's sources, in the form described by the SPDX specification
+  fn spdx_download_location(&self) -> String {
+    let source_details = match self.crate_context {
+      Some(ctx) => &ctx.source_details,
+      None => return "NOASSERTION".to_owned(),
+    };
+
+    if let Some(git_data) = &source_details.git_data {
+      let mut location = format!("git+{}@{}", git_data.remote, git_data.commit);
+      if let Some(path) = &git_data.path_to_crate_root {
+        location += &format!("#{}", path);
+      }
+      location
+    } else if let Some(url) = &source_details.download_url {
+      url.to_string()
+    } else {
+      "NOASSERTION".to_owned()
+    }
+  }
+}
+
+/// Returns the package URL of a crate. Build metadata in versions is percent-encoded.
+fn purl(name: &str, version: &Version) -> String {
+  format!(
+    "pkg:cargo/{}@{}",
+    name,
+    version.to_string().replace('+', "%2B")
+  )
+}
+
+/// Returns an SPDX element id of a crate, which may only contain letters, numbers, `.` and `-`.
+/// Ids which had to be escaped are suffixed with a hash of the crate's name and version, so that
+/// crates such as `foo_bar` and `foo-bar` don't share an id.
+fn spdx_id(name: &str, version: &Version) -> String {
+  let unescaped = format!("{}-{}", name, version);
+  let id: String = unescaped
+    .chars()
+    .map(|c| match c {
+      'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+      _ => '-',
+    })
+    .collect();
+  if id == unescaped {
+    format!("SPDXRef-Package-{}", id)
+  } else {
+    format!(
+      "SPDXRef-Package-{}-{}",
+      id,
+      &content_hash(&unescaped)[..SPDX_ID_HASH_LENGTH]
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use camino::Utf8PathBuf;
+
+  use crate::{context::*, features::Features, settings::CrateSettings};
+
+  use super::*;
+
+  fn dummy_dependency(name: &str) -> BuildableDependency {
+    BuildableDependency {
+      buildable_target: format!("@raze__{}__1_0_0//:{}", name, name),
+      name: name.to_owned(),
+      version: Version::new(1, 0, 0),
+      is_proc_macro: false,
+    }
+  }
+
+  fn dummy_crate(name: &str, default_deps: CrateDependencyContext) -> CrateContext {
+    CrateContext {
+      pkg_name: name.to_owned(),
+      pkg_version: Version::new(1, 0, 0),
+      edition: "2018".to_owned(),
+      raze_settings: CrateSettings::default(),
+      canonical_additional_build_file: None,
+      default_deps,
+      targeted_deps: Vec::new(),
+      platform_rustc_settings: BTreeMap::new(),
+      license: LicenseData {
+        name: "MIT".to_owned(),
+        rating: "notice".to_owned(),
+        expression: "MIT".to_owned(),
+        spdx_ids: vec!["MIT".to_owned()],
+      },
+      features: Features::empty(),
+      workspace_path_to_crate: format!("@raze__{}__1_0_0//", name),
+      workspace_member_dependents: Vec::new(),
+      workspace_member_dev_dependents: Vec::new(),
+      workspace_member_build_dependents: Vec::new(),
+      is_workspace_member_dependency: false,
+      is_binary_dependency: false,
+      targets: Vec::new(),
+      build_script_target: None,
+      links: None,
+      homepage: None,
+      repository: None,
+      source_details: SourceDetails {
+        git_data: None,
+        download_url: Some(
+          format!("https://crates.io/api/v1/crates/{}/1.0.0/download", name)
+            .parse()
+            .unwrap(),
+        ),
+        registry_auth: None,
+      },
+      sha256: Some(format!("{}-sha256", name)),
+      license_files: Vec::new(),
+      package_root: Utf8PathBuf::new(),
+      expected_build_path: format!("remote/BUILD.{}-1.0.0.bazel", name),
+      lib_target_name: Some(name.to_owned()),
+      is_proc_macro: false,
+    }
+  }
+
+  fn dummy_member(name: &str, workspace_member_dependencies: Vec<&str>) -> WorkspaceMemberPackage {
+    WorkspaceMemberPackage {
+      name: name.to_owned(),
+      version: Version::new(0, 1, 0),
+      path: Utf8PathBuf::from(name),
+      workspace_member_dependencies: workspace_member_dependencies
+        .into_iter()
+        .map(Utf8PathBuf::from)
+        .collect(),
+    }
+  }
+
+  /// The `app` member depends on the `lib` member and crate `a`, which depends on `b` and, on
+  /// some platforms, `c`. The `lib` member depends on crate `d` and has a dev dependency on `e`.
+  fn dummy_planned_build() -> PlannedBuild {
+    let mut a = dummy_crate(
+      "a",
+      CrateDependencyContext {
+        dependencies: vec![dummy_dependency("b")].into_iter().collect(),
+        ..CrateDependencyContext::default()
+      },
+    );
+    a.workspace_member_dependents = vec![Utf8PathBuf::from("app")];
+    a.targeted_deps = vec![CrateTargetedDepContext {
+      target: "cfg(unix)".to_owned(),
+      deps: CrateDependencyContext {
+        build_dependencies: vec![dummy_dependency("c")].into_iter().collect(),
+        ..CrateDependencyContext::default()
+      },
+      platform_targets: vec!["x86_64-unknown-linux-gnu".to_owned()],
+    }];
+
+    let mut c = dummy_crate("c", CrateDependencyContext::default());
+    c.source_details = SourceDetails {
+      git_data: Some(GitRepo {
+        remote: "https://github.com/example/c.git".to_owned(),
+        commit: "abcdef".to_owned(),
+        path_to_crate_root: Some("crates/c".to_owned()),
+      }),
+      download_url: None,
+      registry_auth: None,
+    };
+    c.sha256 = None;
+
+    let mut d = dummy_crate("d", CrateDependencyContext::default());
+    d.workspace_member_dependents = vec![Utf8PathBuf::from("lib")];
+
+    let mut e = dummy_crate("e", CrateDependencyContext::default());
+    e.workspace_member_dev_dependents = vec![Utf8PathBuf::from("lib")];
+
+    PlannedBuild {
+      workspace_context: WorkspaceContext {
+        workspace_path: "//cargo".to_owned(),
+        gen_workspace_prefix: "raze".to_owned(),
+        output_buildfile_suffix: "BUILD.bazel".to_owned(),
+        workspace_members: vec![Utf8PathBuf::from("app"), Utf8PathBuf::from("lib")],
+        workspace_member_packages: vec![
+          dummy_member("app", vec!["lib"]),
+          dummy_member("lib", Vec::new()),
+        ],
+        custom_platforms: BTreeMap::new(),
+      },
+      crate_contexts: vec![
+        a,
+        dummy_crate("b", CrateDependencyContext::default()),
+        c,
+        d,
+        e,
+      ],
+      workspace_aliases: Vec::new(),
+      lockfile: None,
+    }
+  }
+
+  fn package_names(sbom: &Sbom) -> Vec<String> {
+    sbom
+      .packages
+      .iter()
+      .map(|package| format!("{}-{}", package.name, package.version))
+      .collect()
+  }
+
+  #[test]
+  fn sbom_contains_the_transitive_closure_of_a_member() {
+    let planned_build = dummy_planned_build();
+    let members = &planned_build.workspace_context.workspace_member_packages;
+
+    let app = Sbom::for_member(&members[0], &planned_build);
+    assert_eq!(
+      package_names(&app),
+      vec!["a-1.0.0", "b-1.0.0", "c-1.0.0", "d-1.0.0", "lib-0.1.0"]
+    );
+    assert_eq!(
+      app.root.dependencies.iter().collect::<Vec<_>>(),
+      vec![
+        &("a", &Version::new(1, 0, 0)),
+        &("lib", &Version::new(0, 1, 0))
+      ]
+    );
+
+    // Development dependencies are not included
+    let lib = Sbom::for_member(&members[1], &planned_build);
+    assert_eq!(package_names(&lib), vec!["d-1.0.0"]);
+  }
+
+  #[test]
+  fn cyclonedx_document_describes_components_and_dependencies() {
+    let planned_build = dummy_planned_build();
+    let document = Sbom::for_member(
+      &planned_build.workspace_context.workspace_member_packages[0],
+      &planned_build,
+    )
+    .to_cyclonedx();
+
+    assert_eq!(document["bomFormat"], "CycloneDX");
+    assert_eq!(
+      document["metadata"]["component"]["purl"],
+      "pkg:cargo/app@0.1.0"
+    );
+
+    let components = document["components"].as_array().unwrap();
+    assert_eq!(components.len(), 5);
+    assert_eq!(
+      components[0],
+      json!({
+        "type": "library",
+        "bom-ref": "pkg:cargo/a@1.0.0",
+        "name": "a",
+        "version": "1.0.0",
+        "purl": "pkg:cargo/a@1.0.0",
+        "hashes": [{ "alg": "SHA-256", "content": "a-sha256" }],
+        "licenses": [{ "expression": "MIT" }],
+        "externalReferences": [{
+          "type": "distribution",
+          "url": "https://crates.io/api/v1/crates/a/1.0.0/download",
+        }],
+      })
+    );
+    assert_eq!(
+      components[2]["externalReferences"],
+      json!([{
+        "type": "vcs",
+        "url": "https://github.com/example/c.git",
+        "comment": "commit abcdef",
+      }])
+    );
+
+    assert!(document["dependencies"]
+      .as_array()
+      .unwrap()
+      .contains(&json!({
+        "ref": "pkg:cargo/a@1.0.0",
+        "dependsOn": ["pkg:cargo/b@1.0.0", "pkg:cargo/c@1.0.0"],
+      })));
+  }
+
+  #[test]
+  fn spdx_document_describes_packages_and_relationships() {
+    let planned_build = dummy_planned_build();
+    let sbom = Sbom::for_member(
+      &planned_build.workspace_context.workspace_member_packages[0],
+      &planned_build,
+    );
+    let document = sbom.to_spdx();
+
+    assert_eq!(document["spdxVersion"], "SPDX-2.3");
+    assert_eq!(document["creationInfo"]["created"], SPDX_CREATION_TIME);
+
+    let packages = document["packages"].as_array().unwrap();
+    assert_eq!(packages[0]["SPDXID"], "SPDXRef-Package-app-0.1.0");
+    assert_eq!(packages[0]["downloadLocation"], "NOASSERTION");
+    assert_eq!(packages[0]["licenseDeclared"], "NOASSERTION");
+    assert_eq!(
+      packages[1]["checksums"],
+      json!([{ "algorithm": "SHA256", "checksumValue": "a-sha256" }])
+    );
+    assert_eq!(
+      packages[3]["downloadLocation"],
+      "git+https://github.com/example/c.git@abcdef#crates/c"
+    );
+
+    let relationships = document["relationships"].as_array().unwrap();
+    assert_eq!(
+      relationships[0],
+      json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-Package-app-0.1.0",
+      })
+    );
+    assert!(relationships.contains(&json!({
+      "spdxElementId": "SPDXRef-Package-app-0.1.0",
+      "relationshipType": "DEPENDS_ON",
+      "relatedSpdxElement": "SPDXRef-Package-lib-0.1.0",
+    })));
+
+    // Rendering is reproducible
+    assert_eq!(document, sbom.to_spdx());
+  }
+
+  #[test]
+  fn ids_are_escaped() {
+    let version = Version::parse("1.0.0+build.1").unwrap();
+    assert_eq!(purl("a", &version), "pkg:cargo/a@1.0.0%2Bbuild.1");
+    assert_eq!(
+      spdx_id("a_b", &version),
+      format!(
+        "SPDXRef-Package-a-b-1.0.0-build.1-{}",
+        &content_hash("a_b-1.0.0+build.1")[..SPDX_ID_HASH_LENGTH]
+      )
+    );
+
+    // Escaping never maps distinct crates to the same id
+    let version = Version::parse("1.0.0").unwrap();
+    assert_eq!(
+      spdx_id("foo-bar", &version),
+      "SPDXRef-Package-foo-bar-1.0.0"
+    );
+    assert_ne!(spdx_id("foo_bar", &version), spdx_id("foo-bar", &version));
+  }
+
+  #[test]
+  fn spdx_namespace_is_a_sha256_of_the_document() {
+    let planned_build = dummy_planned_build();
+    let sbom = Sbom::for_member(
+      &planned_build.workspace_context.workspace_member_packages[0],
+      &planned_build,
+    );
+    let document = sbom.to_spdx();
+
+    let namespace = document["documentNamespace"].as_str().unwrap();
+    let namespace_hash = namespace
+      .strip_prefix("https://github.com/google/cargo-raze/spdx/app-0.1.0-")
+      .unwrap();
+    assert_eq!(
+      namespace_hash,
+      content_hash(&json!([document["packages"], document["relationships"]]).to_string())
+    );
+    assert_eq!(namespace_hash.len(), 64);
+    assert!(namespace_hash.chars().all(|c| c.is_ascii_hexdigit()));
+  }
+}
@@ -15,17 +15,19 @@
 use crate::{
   error::RazeError,
   metadata::{MetadataFetcher, DEFAULT_CRATE_INDEX_URL, DEFAULT_CRATE_REGISTRY_URL},
+  planning::license::BazelLicenseType,
   util,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use cargo_metadata::{Metadata, MetadataCommand, Package};
-use semver::VersionReq;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::{
   collections::{BTreeMap, BTreeSet, HashMap, HashSet},
   hash::Hash,
 };
+use url::Url;
 
 pub type CrateSettingsPerVersion = HashMap<VersionReq, CrateSettings>;
 
@@ -64,6 +66,15 @@ pub struct RazeSettings {
   #[serde(default)]
   pub targets: Option<HashSet<String>>,
 
+  /// Additional platforms to generate BUILD rules for, beyond those defined in rules_rust.
+  ///
+  /// Each entry maps a target triple to the label of the Bazel `config_setting` or platform
+  /// constraint which selects it, eg: `"riscv64gc-unknown-linux-gnu" = "//platforms:riscv64"`.
+  /// These are subject to the `targets` allowlist like any other platform. Triples unknown to
+  /// Rust only match dependencies which name them explicitly, as their cfg values are unknown.
+  #[serde(default)]
+  pub custom_platforms: BTreeMap<String, String>,
+
   /// A list of binary dependencies.
   #[serde(default)]
   pub binary_deps: HashMap<String, cargo_toml::Dependency>,
@@ -105,7 +116,7 @@ pub struct RazeSettings {
   #[serde(default = "default_raze_settings_registry")]
   pub registry: String,
 
-  /// The index url to use for Binary dependencies
+  /// The index url to use for Binary dependencies. Sparse indexes are prefixed with `sparse+`
   #[serde(default = "default_raze_settings_index_url")]
   pub index_url: String,
 
@@ -126,6 +137,92 @@ pub struct RazeSettings {
    */
   #[serde(default = "default_raze_settings_experimental_api")]
   pub experimental_api: bool,
+
+  /// If true, files written by a previous run of cargo-raze which are no longer generated will be
+  /// deleted, along with the directories of vendored crates which are no longer dependencies.
+  ///
+  /// Enabled by default. When disabled, these files are only reported. Previous outputs are tracked
+  /// in a `.cargo-raze-manifest` file written next to `crates.bzl`, and only files within that
+  /// directory are ever deleted.
+  #[serde(default = "default_raze_settings_prune_stale_outputs")]
+  pub prune_stale_outputs: bool,
+
+  /// If true, an `extensions.bzl` file defining a Bazel module extension for the remote crates
+  /// will be rendered next to `crates.bzl` for use with `MODULE.bazel` (bzlmod).
+  ///
+  /// This has no effect unless the GenMode setting is Remote.
+  #[serde(default = "default_raze_settings_render_module_extension")]
+  pub render_module_extension: bool,
+
+  /// If true, the BUILD files of crates whose inputs are unchanged since the last run are reused
+  /// instead of being rendered again.
+  ///
+  /// A hash of each crate's inputs is stored in a `.cargo-raze-cache` file written next to
+  /// `crates.bzl`. The cache is ignored when running with `--check`.
+  #[serde(default = "default_raze_settings_incremental_rendering")]
+  pub incremental_rendering: bool,
+
+  /// Restrictions on the licenses of third-party crates. See LicensePolicy for details.
+  #[serde(default)]
+  pub license_policy: LicensePolicy,
+
+  /// If set, an attribution document collecting the license expression and license files of every
+  /// crate is rendered into a `licenses` package next to `crates.bzl`, in the given format.
+  ///
+  /// The package also contains a `license_files` filegroup of every crate's license files.
+  #[serde(default)]
+  pub license_report: Option<LicenseReportFormat>,
+
+  /// If true, each crate's BUILD file will contain [rules_license](https://github.com/bazelbuild/rules_license)
+  /// `license` and `package_info` targets, which are applied to the crate's targets through
+  /// `default_package_metadata`.
+  ///
+  /// This requires the `rules_license` repository to be available.
+  #[serde(default = "default_raze_settings_render_license_targets")]
+  pub render_license_targets: bool,
+
+  /// The software bill of materials formats to render for each workspace member.
+  ///
+  /// Each document lists the crates a member transitively depends on, excluding development
+  /// dependencies, along with their checksums, sources, licenses and dependency edges. Documents
+  /// are rendered into an `sbom` package next to `crates.bzl`.
+  #[serde(default)]
+  pub sbom_formats: Vec<SbomFormat>,
+
+  /// If true, cargo-raze never accesses the network.
+  ///
+  /// Cargo is run with `--offline` and crate checksums are only read from the lockfile or a crate
+  /// index already on disk. Anything that would need the network, such as downloading binary
+  /// dependencies, is reported as an error instead. This can also be enabled with `--offline`.
+  #[serde(default = "default_raze_settings_offline")]
+  pub offline: bool,
+
+  /// Credentials for registries which require authentication to download crates, keyed by an
+  /// arbitrary registry name. See RegistryAuth for details.
+  #[serde(default)]
+  pub registry_auth: BTreeMap<String, RegistryAuth>,
+
+  /// How cargo picks the versions of dependencies when a new lockfile is generated. See
+  /// VersionResolution for details. This can also be set with `--version-resolution`.
+  #[serde(default)]
+  pub version_resolution: VersionResolution,
+
+  /// Exact versions to pin crates to when a new lockfile is generated, keyed by crate name.
+  ///
+  /// Each pin is applied with `cargo update -p {name} --precise {version}` once the lockfile has
+  /// been generated. Reused lockfiles are left as they are.
+  #[serde(default)]
+  pub precise_versions: BTreeMap<String, Version>,
+
+  /// If true, cargo-raze runs `cargo vendor --versioned-dirs --no-delete` into `vendor_dir`
+  /// itself, and verifies each vendored crate's `.cargo-checksum.json` against the lockfile and its
+  /// files against that `.cargo-checksum.json`, before rendering. Crates already in `vendor_dir`
+  /// are never removed by `cargo vendor`.
+  ///
+  /// Crates are not vendored when running with `--check` or `--dryrun`. This has no effect unless
+  /// the GenMode setting is Vendored.
+  #[serde(default = "default_raze_settings_run_cargo_vendor")]
+  pub run_cargo_vendor: bool,
 }
 
 impl RazeSettings {
@@ -135,10 +232,20 @@ impl RazeSettings {
     if let Some(target) = &self.target {
       result.insert(target.into());
     } else {
-      result.extend(util::get_enabled_targets(&self.targets).map(String::from));
+      result
+        .extend(util::get_enabled_targets(&self.targets, &self.custom_platforms).map(String::from));
     }
     result
   }
+
+  /// Returns the credentials of the registry crates are downloaded from at the given URL
+  pub fn registry_auth_for(&self, download_url: &Url) -> Option<&RegistryAuth> {
+    let host = download_url.host_str()?;
+    self
+      .registry_auth
+      .values()
+      .find(|auth| auth.host.eq_ignore_ascii_case(host))
+  }
 }
 
 /// Override settings for individual crates (as part of `RazeSettings`).
@@ -176,6 +283,13 @@ pub struct CrateSettings {
   #[serde(default)]
   pub additional_env: BTreeMap<String, String>,
 
+  /// Flags and environment variables to be added to the crate compilation process only on
+  /// matching platforms.
+  ///
+  /// Keys are either a target triple or a `cfg(...)` expression, such as `cfg(windows)`.
+  #[serde(default)]
+  pub targeted_rustc_settings: BTreeMap<String, TargetedRustcSettings>,
+
   /// Whether or not to generate the build script that goes with this crate.
   ///
   /// Many build scripts will not function, as they will still be built hermetically. However, build
@@ -212,6 +326,20 @@ pub struct CrateSettings {
   #[serde(default)]
   pub buildrs_additional_deps: Vec<String>,
 
+  /// Whether or not to generate `rust_test` targets for the crate's library and integration tests,
+  /// and `rust_binary` targets for its examples.
+  ///
+  /// Examples are built as binaries, as `cargo test` does, since they rarely contain tests. These
+  /// targets depend on the crate's dev-dependencies, including platform specific ones. Cargo only
+  /// resolves these for workspace members, so any needed by a third-party crate must be provided
+  /// via `test_additional_deps`.
+  #[serde(default)]
+  pub gen_tests: bool,
+
+  /// Additional dependencies for test targets. See `additional_deps`
+  #[serde(default)]
+  pub test_additional_deps: Vec<String>,
+
   /// The arguments given to the patch tool.
   ///
   /// Defaults to `-p0`, however `-p1` will usually be needed for patches generated by git.
@@ -263,8 +391,126 @@ pub struct CrateSettings {
   pub additional_build_file: Option<Utf8PathBuf>,
 }
 
+/// Platform specific compilation settings for a crate. See `CrateSettings::targeted_rustc_settings`
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetedRustcSettings {
+  /// Flags to be added to the crate compilation process, in the form "--flag".
+  #[serde(default)]
+  pub additional_flags: Vec<String>,
+
+  /// Environment variables to be added to the crate compilation process.
+  #[serde(default)]
+  pub additional_env: BTreeMap<String, String>,
+}
+
+/// Restrictions on the licenses third-party crates may be used under (as part of `RazeSettings`).
+///
+/// Crates whose license expression cannot be satisfied using only licenses permitted by the policy
+/// will cause cargo-raze to fail.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LicensePolicy {
+  /// SPDX license ids which crates may be used under. If empty, any license which is not denied
+  /// is allowed.
+  ///
+  /// GNU licenses are given without their `-only` or `-or-later` suffix (eg: "GPL-3.0") and match
+  /// either form.
+  #[serde(default)]
+  pub allowed: Vec<String>,
+
+  /// SPDX license ids which crates may never be used under. See `allowed`.
+  #[serde(default)]
+  pub denied: Vec<String>,
+
+  /// The most restrictive license rating crates may be used under, eg: "reciprocal".
+  #[serde(default)]
+  pub max_rating: Option<BazelLicenseType>,
+
+  /// Crates which are exempt from the policy. Entries are either a crate name (eg: "ring") or a
+  /// crate name and version (eg: "ring-0.16.20").
+  #[serde(default)]
+  pub exceptions: Vec<String>,
+
+  /// If true, crates which only declare a `license-file` rather than a license expression are
+  /// permitted, with a warning to review the file by hand. Otherwise they violate the policy.
+  #[serde(default)]
+  pub allow_license_files: bool,
+}
+
+impl LicensePolicy {
+  /// Returns whether or not the policy places any restrictions on licenses
+  pub fn is_empty(&self) -> bool {
+    self.allowed.is_empty() && self.denied.is_empty() && self.max_rating.is_none()
+  }
+
+  /// Returns whether or not the given crate is exempt from the policy
+  pub fn is_exception(&self, name: &str, version: &str) -> bool {
+    self
+      .exceptions
+      .iter()
+      .any(|exception| exception == name || *exception == util::package_ident(name, version))
+  }
+}
+
+/// The formats a license report can be rendered in. See `RazeSettings::license_report`
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum LicenseReportFormat {
+  /// A `THIRD_PARTY_LICENSES.md` Markdown document
+  Markdown,
+  /// A `THIRD_PARTY_LICENSES.html` HTML document
+  Html,
+  /// A `THIRD_PARTY_LICENSES.json` file for consumption by other tools
+  Json,
+}
+
+/// The strategies cargo can resolve dependency versions with. See
+/// `RazeSettings::version_resolution`
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum VersionResolution {
+  /// The newest version matching each requirement, as picked by `cargo generate-lockfile`
+  #[default]
+  Maximal,
+  /// The oldest version matching each requirement, as picked with `-Z minimal-versions`
+  Minimal,
+  /// The oldest versions matching the requirements of workspace members, and the newest versions
+  /// of all other crates, as picked with `-Z direct-minimal-versions`
+  DirectMinimal,
+}
+
+/// How Bazel authenticates to a registry when downloading its crates.
+///
+/// These are rendered into the `auth_patterns` and `netrc` attributes of the `http_archive` of
+/// every crate downloaded from `host`. Bazel substitutes `<login>` and `<password>` in the
+/// `auth_pattern` with the credentials of the matching machine in the netrc file.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryAuth {
+  /// The host crates are downloaded from, eg: "crates.example.com"
+  pub host: String,
+
+  /// The template of the `Authorization` header sent to `host`, eg: "Bearer <password>"
+  #[serde(default = "default_registry_auth_pattern")]
+  pub auth_pattern: String,
+
+  /// The netrc file Bazel reads credentials from. If unset, Bazel uses `~/.netrc`
+  #[serde(default)]
+  pub netrc: Option<String>,
+}
+
+/// The software bill of materials formats. See `RazeSettings::sbom_formats`
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SbomFormat {
+  /// A CycloneDX 1.4 JSON document named `{member}.cdx.json`
+  #[serde(rename = "CycloneDX")]
+  CycloneDx,
+  /// An SPDX 2.3 JSON document named `{member}.spdx.json`
+  #[serde(rename = "SPDX")]
+  Spdx,
+}
+
 /// Describes how dependencies should be managed in tree.
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub enum GenMode {
   /// This mode assumes that files are vendored (into vendor/), and generates BUILD files
   /// accordingly
@@ -284,6 +530,7 @@ impl Default for CrateSettings {
       extra_aliased_targets: Vec::new(),
       additional_flags: Vec::new(),
       additional_env: BTreeMap::new(),
+      targeted_rustc_settings: BTreeMap::new(),
       gen_buildrs: default_crate_settings_field_gen_buildrs(),
       data_attr: default_crate_settings_field_data_attr(),
       data_dependencies: Vec::new(),
@@ -292,6 +539,8 @@ impl Default for CrateSettings {
       build_tools_dependencies: Vec::new(),
       buildrs_additional_deps: Vec::new(),
       buildrs_additional_environment_variables: BTreeMap::new(),
+      gen_tests: false,
+      test_additional_deps: Vec::new(),
       patch_args: Vec::new(),
       patch_cmds: Vec::new(),
       patch_cmds_win: Vec::new(),
@@ -341,6 +590,34 @@ fn default_raze_settings_experimental_api() -> bool {
   false
 }
 
+fn default_raze_settings_prune_stale_outputs() -> bool {
+  true
+}
+
+fn default_raze_settings_render_module_extension() -> bool {
+  false
+}
+
+fn default_raze_settings_render_license_targets() -> bool {
+  false
+}
+
+fn default_raze_settings_offline() -> bool {
+  false
+}
+
+fn default_raze_settings_run_cargo_vendor() -> bool {
+  false
+}
+
+fn default_registry_auth_pattern() -> String {
+  "Bearer <password>".to_owned()
+}
+
+fn default_raze_settings_incremental_rendering() -> bool {
+  false
+}
+
 fn default_crate_settings_field_gen_buildrs() -> Option<bool> {
   None
 }
@@ -443,6 +720,37 @@ fn validate_settings(
     settings.workspace_path.pop();
   }
 
+  for (triple, label) in settings.custom_platforms.iter() {
+    if !label.starts_with("//") && !label.starts_with('@') {
+      return Err(RazeError::Config {
+        field_path_opt: Some(format!("raze.custom_platforms.{}", triple)),
+        message: format!(
+          "The label `{}` must be absolute, starting with \"//\" or \"@\".",
+          label
+        ),
+      });
+    }
+  }
+
+  let license_policy_ids = [
+    ("allowed", &settings.license_policy.allowed),
+    ("denied", &settings.license_policy.denied),
+  ];
+  for (field, license_ids) in license_policy_ids.iter() {
+    for license_id in license_ids.iter() {
+      if spdx::Licensee::parse(license_id).is_err() {
+        return Err(RazeError::Config {
+          field_path_opt: Some(format!("raze.license_policy.{}", field)),
+          message: format!(
+            "`{}` is not a valid SPDX license id. GNU licenses must not include an `-only` or \
+             `-or-later` suffix.",
+            license_id
+          ),
+        });
+      }
+    }
+  }
+
   if settings.genmode == GenMode::Unspecified {
     eprintln!(
       "WARNING: The [raze] setting `genmode` is unspecified. Not specifying `genmode` is \
@@ -474,6 +782,8 @@ struct RawRazeSettings {
   #[serde(default)]
   pub targets: Option<Vec<String>>,
   #[serde(default)]
+  pub custom_platforms: Option<BTreeMap<String, String>>,
+  #[serde(default)]
   pub binary_deps: HashMap<String, cargo_toml::Dependency>,
   #[serde(default)]
   pub crates: HashMap<String, CrateSettingsPerVersion>,
@@ -495,6 +805,30 @@ struct RawRazeSettings {
   pub vendor_dir: Option<String>,
   #[serde(default)]
   pub experimental_api: Option<bool>,
+  #[serde(default)]
+  pub prune_stale_outputs: Option<bool>,
+  #[serde(default)]
+  pub render_module_extension: Option<bool>,
+  #[serde(default)]
+  pub incremental_rendering: Option<bool>,
+  #[serde(default)]
+  pub license_policy: Option<LicensePolicy>,
+  #[serde(default)]
+  pub license_report: Option<LicenseReportFormat>,
+  #[serde(default)]
+  pub render_license_targets: Option<bool>,
+  #[serde(default)]
+  pub sbom_formats: Option<Vec<SbomFormat>>,
+  #[serde(default)]
+  pub offline: Option<bool>,
+  #[serde(default)]
+  pub registry_auth: Option<BTreeMap<String, RegistryAuth>>,
+  #[serde(default)]
+  pub version_resolution: Option<VersionResolution>,
+  #[serde(default)]
+  pub precise_versions: Option<BTreeMap<String, Version>>,
+  #[serde(default)]
+  pub run_cargo_vendor: Option<bool>,
 }
 
 impl RawRazeSettings {
@@ -505,6 +839,7 @@ impl RawRazeSettings {
       || self.render_package_aliases.is_some()
       || self.target.is_some()
       || self.targets.is_some()
+      || self.custom_platforms.is_some()
       || self.gen_workspace_prefix.is_some()
       || self.genmode.is_some()
       || self.output_buildfile_suffix.is_some()
@@ -514,6 +849,18 @@ impl RawRazeSettings {
       || self.rust_rules_workspace_name.is_some()
       || self.vendor_dir.is_some()
       || self.experimental_api.is_some()
+      || self.prune_stale_outputs.is_some()
+      || self.render_module_extension.is_some()
+      || self.incremental_rendering.is_some()
+      || self.license_policy.is_some()
+      || self.license_report.is_some()
+      || self.render_license_targets.is_some()
+      || self.sbom_formats.is_some()
+      || self.offline.is_some()
+      || self.registry_auth.is_some()
+      || self.version_resolution.is_some()
+      || self.precise_versions.is_some()
+      || self.run_cargo_vendor.is_some()
   }
 
   fn print_notices_and_warnings(&self) {
@@ -807,6 +1154,7 @@ pub mod tests {
       render_package_aliases: default_render_package_aliases(),
       target: Some("x86_64-unknown-linux-gnu".to_owned()),
       targets: None,
+      custom_platforms: BTreeMap::new(),
       crates: HashMap::new(),
       gen_workspace_prefix: "raze_test".to_owned(),
       genmode: GenMode::Remote,
@@ -818,6 +1166,18 @@ pub mod tests {
       rust_rules_workspace_name: default_raze_settings_rust_rules_workspace_name(),
       vendor_dir: default_raze_settings_vendor_dir(),
       experimental_api: default_raze_settings_experimental_api(),
+      prune_stale_outputs: default_raze_settings_prune_stale_outputs(),
+      render_module_extension: default_raze_settings_render_module_extension(),
+      incremental_rendering: default_raze_settings_incremental_rendering(),
+      license_policy: LicensePolicy::default(),
+      license_report: None,
+      render_license_targets: default_raze_settings_render_license_targets(),
+      sbom_formats: Vec::new(),
+      offline: default_raze_settings_offline(),
+      registry_auth: BTreeMap::new(),
+      version_resolution: VersionResolution::default(),
+      precise_versions: BTreeMap::new(),
+      run_cargo_vendor: default_raze_settings_run_cargo_vendor(),
     }
   }
 
@@ -876,6 +1236,167 @@ pub mod tests {
 
     let settings = load_settings_from_manifest(cargo_toml_path, None).unwrap();
     assert!(!settings.binary_deps.is_empty());
+    assert!(settings.prune_stale_outputs);
+  }
+
+  #[test]
+  fn test_loading_custom_platforms() {
+    let toml_contents = indoc! { r#"
+    [package]
+    name = "load_settings_test"
+    version = "0.1.0"
+
+    [lib]
+    path = "not_a_file.rs"
+
+    [package.metadata.raze]
+    workspace_path = "//workspace_path/raze"
+    genmode = "Remote"
+    targets = ["riscv64gc-unknown-linux-gnu", "x86_64-unknown-linux-gnu"]
+
+    [package.metadata.raze.custom_platforms]
+    riscv64gc-unknown-linux-gnu = "//platforms:riscv64"
+    thumbv7em-none-eabihf = "@board//:thumbv7em"
+    "# };
+
+    let temp_workspace_dir = TempDir::new().expect("Failed to set up temporary directory");
+    let cargo_toml_path =
+      Utf8PathBuf::from_path_buf(temp_workspace_dir.path().join("Cargo.toml")).unwrap();
+    std::fs::write(&cargo_toml_path, toml_contents).unwrap();
+
+    let settings = load_settings_from_manifest(cargo_toml_path.clone(), None).unwrap();
+    assert_eq!(
+      settings.custom_platforms.get("riscv64gc-unknown-linux-gnu"),
+      Some(&"//platforms:riscv64".to_owned())
+    );
+    // Custom platforms are subject to the `targets` allowlist
+    assert_eq!(
+      settings
+        .enabled_targets()
+        .into_iter()
+        .collect::<Vec<String>>(),
+      vec!["riscv64gc-unknown-linux-gnu", "x86_64-unknown-linux-gnu"]
+    );
+
+    std::fs::write(
+      &cargo_toml_path,
+      toml_contents.replace("\"//platforms:riscv64\"", "\"platforms:riscv64\""),
+    )
+    .unwrap();
+    assert!(load_settings_from_manifest(cargo_toml_path, None).is_err());
+  }
+
+  #[test]
+  fn test_loading_license_policy() {
+    let toml_contents = indoc! { r#"
+    [package]
+    name = "load_settings_test"
+    version = "0.1.0"
+
+    [lib]
+    path = "not_a_file.rs"
+
+    [package.metadata.raze]
+    workspace_path = "//workspace_path/raze"
+    genmode = "Remote"
+
+    [package.metadata.raze.license_policy]
+    denied = ["GPL-3.0"]
+    max_rating = "reciprocal"
+    exceptions = ["ring-0.16.20"]
+    "# };
+
+    let temp_workspace_dir = TempDir::new().expect("Failed to set up temporary directory");
+    let cargo_toml_path =
+      Utf8PathBuf::from_path_buf(temp_workspace_dir.path().join("Cargo.toml")).unwrap();
+    std::fs::write(&cargo_toml_path, toml_contents).unwrap();
+
+    let settings = load_settings_from_manifest(cargo_toml_path.clone(), None).unwrap();
+    let policy = settings.license_policy;
+    assert_eq!(policy.max_rating, Some(BazelLicenseType::Reciprocal));
+    assert!(policy.is_exception("ring", "0.16.20"));
+    assert!(!policy.is_exception("ring", "0.17.0"));
+
+    std::fs::write(
+      &cargo_toml_path,
+      toml_contents.replace("GPL-3.0", "GPL-3.0-only"),
+    )
+    .unwrap();
+    assert!(load_settings_from_manifest(cargo_toml_path, None).is_err());
+  }
+
+  #[test]
+  fn test_loading_version_resolution() {
+    let toml_contents = indoc! { r#"
+    [package]
+    name = "load_settings_test"
+    version = "0.1.0"
+
+    [lib]
+    path = "not_a_file.rs"
+
+    [package.metadata.raze]
+    workspace_path = "//workspace_path/raze"
+    genmode = "Remote"
+    version_resolution = "DirectMinimal"
+
+    [package.metadata.raze.precise_versions]
+    openssl = "0.10.55"
+    "# };
+
+    let temp_workspace_dir = TempDir::new().expect("Failed to set up temporary directory");
+    let cargo_toml_path =
+      Utf8PathBuf::from_path_buf(temp_workspace_dir.path().join("Cargo.toml")).unwrap();
+    std::fs::write(&cargo_toml_path, toml_contents).unwrap();
+
+    let settings = load_settings_from_manifest(cargo_toml_path, None).unwrap();
+    assert_eq!(
+      settings.version_resolution,
+      VersionResolution::DirectMinimal
+    );
+    assert_eq!(
+      settings.precise_versions.get("openssl"),
+      Some(&Version::new(0, 10, 55))
+    );
+  }
+
+  #[test]
+  fn test_loading_registry_auth() {
+    let toml_contents = indoc! { r#"
+    [package]
+    name = "load_settings_test"
+    version = "0.1.0"
+
+    [lib]
+    path = "not_a_file.rs"
+
+    [package.metadata.raze]
+    workspace_path = "//workspace_path/raze"
+    genmode = "Remote"
+
+    [package.metadata.raze.registry_auth.internal]
+    host = "crates.example.com"
+    netrc = "/etc/raze.netrc"
+    "# };
+
+    let temp_workspace_dir = TempDir::new().expect("Failed to set up temporary directory");
+    let cargo_toml_path =
+      Utf8PathBuf::from_path_buf(temp_workspace_dir.path().join("Cargo.toml")).unwrap();
+    std::fs::write(&cargo_toml_path, toml_contents).unwrap();
+
+    let settings = load_settings_from_manifest(cargo_toml_path, None).unwrap();
+    let url = Url::parse("https://crates.example.com/api/v1/crates/foo/1.0.0/download").unwrap();
+    assert_eq!(
+      settings.registry_auth_for(&url),
+      Some(&RegistryAuth {
+        host: "crates.example.com".to_owned(),
+        auth_pattern: "Bearer <password>".to_owned(),
+        netrc: Some("/etc/raze.netrc".to_owned()),
+      })
+    );
+
+    let url = Url::parse("https://crates.io/api/v1/crates/foo/1.0.0/download").unwrap();
+    assert!(settings.registry_auth_for(&url).is_none());
   }
 
   #[test]
@@ -24,6 +24,7 @@ use std::{
   collections::HashMap,
   fs::{create_dir_all, write, File},
   io::Write,
+  process::Command,
 };
 
 use crate::{
@@ -262,6 +263,90 @@ pub fn mock_remote_crate<'server>(
   }
 }
 
+/// Creates a git repository containing a binary crate in the `crate_dir` subdirectory, returning
+/// the repository and the commit it is at.
+pub fn mock_git_crate(name: &str, version: &str, crate_dir: &str) -> (TempDir, String) {
+  let repo = TempDir::new().unwrap();
+  let crate_path = repo.as_ref().join(crate_dir);
+  create_dir_all(crate_path.join("src")).unwrap();
+  write(
+    crate_path.join("Cargo.toml"),
+    formatdoc! { r#"
+      [package]
+      name = "{name}"
+      version = "{version}"
+
+      [[bin]]
+      name = "{name}"
+      path = "src/main.rs"
+    "#, name = name, version = version },
+  )
+  .unwrap();
+  write(crate_path.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+  let git = |args: &[&str]| {
+    let output = Command::new("git")
+      .args(["-c", "user.name=raze", "-c", "user.email=raze@example.com"])
+      .args(args)
+      .current_dir(repo.as_ref())
+      .output()
+      .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+  };
+  git(&["init", "--quiet"]);
+  git(&["add", "."]);
+  git(&["commit", "--quiet", "-m", "Initial commit"]);
+  let commit = git(&["rev-parse", "HEAD"]).trim().to_owned();
+
+  (repo, commit)
+}
+
+/// Configures the given mock_server to serve a sparse crate index containing a single crate, whose
+/// name must be at least 4 characters long. The checksum of each version is its digits followed by
+/// zeros.
+///
+/// Returns the mocks of the index's `config.json` and the crate's entry.
+pub fn mock_sparse_index<'server>(
+  mock_server: &'server MockServer,
+  dl: &str,
+  name: &str,
+  versions: &[&str],
+) -> Vec<MockRef<'server>> {
+  let mock_config = mock_server.mock(|when, then| {
+    when.method(GET).path("/config.json");
+    then.status(200).json_body(json!({ "dl": dl }));
+  });
+
+  let entry = versions
+    .iter()
+    .map(|version| {
+      let checksum = format!("{:0<64}", version.replace('.', ""));
+      json!({
+        "name": name,
+        "vers": version,
+        "deps": [],
+        "features": {},
+        "cksum": checksum,
+        "yanked": false,
+      })
+      .to_string()
+    })
+    .collect::<Vec<String>>()
+    .join("\n");
+  let mock_crate = mock_server.mock(|when, then| {
+    when.method(GET).path(format!(
+      "/{}/{}/{}",
+      &name[..2],
+      &name[2..4],
+      name.to_ascii_lowercase()
+    ));
+    then.status(200).body(entry);
+  });
+
+  vec![mock_config, mock_crate]
+}
+
 /// A helper macro for passing a `crates` to  `mock_crate_index`
 pub fn to_index_crates_map(list: Vec<(&str, &str)>) -> HashMap<String, String> {
   list
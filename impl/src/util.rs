@@ -13,7 +13,13 @@
 // limitations under the License.
 
 use anyhow::Result;
-use std::{collections::HashSet, env, fmt, iter::Iterator, process::Command, str::FromStr};
+use std::{
+  collections::{BTreeMap, HashSet},
+  env, fmt,
+  iter::Iterator,
+  process::Command,
+  str::FromStr,
+};
 
 use camino::{Utf8Path, Utf8PathBuf};
 use cargo_platform::Cfg;
@@ -76,8 +82,38 @@ pub enum BazelTargetSupport {
   Unsupported,
 }
 
-/// Determines if the target matches those supported by and defined in rules_rust
-pub fn is_bazel_supported_platform(target: &str) -> BazelTargetSupport {
+/// Returns the triples of all supported platforms: those defined in rules_rust, followed by any
+/// custom platforms which are not already among them.
+fn supported_platform_triples<'a>(
+  custom_platforms: &'a BTreeMap<String, String>,
+) -> impl Iterator<Item = &'a str> + 'a {
+  SUPPORTED_PLATFORM_TRIPLES.iter().copied().chain(
+    custom_platforms
+      .keys()
+      .map(String::as_str)
+      .filter(|triple| !SUPPORTED_PLATFORM_TRIPLES.contains(triple)),
+  )
+}
+
+/// Evaluates a target expression against a triple.
+///
+/// Triples unknown to `cfg-expr` can only be matched by name, as their cfg attributes are unknown.
+fn triple_matches_expression(expression: &Expression, triple: &str) -> bool {
+  let target_info = get_builtin_target_by_triple(triple);
+  expression.eval(|pred| match pred {
+    Predicate::Target(tp) => matches!(target_info, Some(target_info) if tp.matches(target_info)),
+    Predicate::KeyValue { key, val } => (*key == "target") && (*val == triple),
+    // For now there is no other kind of matching
+    _ => false,
+  })
+}
+
+/// Determines if the target matches those supported by and defined in rules_rust, or those of the
+/// provided custom platforms
+pub fn is_bazel_supported_platform(
+  target: &str,
+  custom_platforms: &BTreeMap<String, String>,
+) -> BazelTargetSupport {
   // Ensure the target is represented as an expression
   let target_exp = match target.starts_with("cfg(") {
     true => target.to_owned(),
@@ -94,22 +130,8 @@ pub fn is_bazel_supported_platform(target: &str) -> BazelTargetSupport {
   let mut matches_all = true;
 
   // Attempt to match the expression
-  for target_info in SUPPORTED_PLATFORM_TRIPLES
-    .iter()
-    .map(|x| get_builtin_target_by_triple(x).unwrap())
-  {
-    let target_matches = expression.eval(|pred| {
-      match pred {
-        Predicate::Target(tp) => tp.matches(target_info),
-        Predicate::KeyValue { key, val } => {
-          (*key == "target") && (*val == target_info.triple.as_str())
-        }
-        // For now there is no other kind of matching
-        _ => false,
-      }
-    });
-
-    if target_matches {
+  for triple in supported_platform_triples(custom_platforms) {
+    if triple_matches_expression(&expression, triple) {
       specific_match = true;
     } else {
       matches_all = false;
@@ -125,34 +147,21 @@ pub fn is_bazel_supported_platform(target: &str) -> BazelTargetSupport {
 
 /// Maps a Rust cfg or triple target to Bazel supported triples.
 ///
-/// Note, the Bazel triples must be defined in:
+/// Note, the Bazel triples must either be defined in:
 /// https://github.com/bazelbuild/rules_rust/blob/master/rust/platform/platform.bzl
+/// or be one of the provided custom platforms.
 pub fn get_matching_bazel_triples<'a>(
   target: &str,
   allowlist: &'a Option<HashSet<String>>,
-) -> Result<impl Iterator<Item = &'static str> + 'a> {
+  custom_platforms: &'a BTreeMap<String, String>,
+) -> Result<impl Iterator<Item = &'a str> + 'a> {
   let expression = match target.starts_with("cfg(") {
     true => Expression::parse(target),
     false => Expression::parse(&format!("cfg(target = \"{}\")", target)),
   }?;
 
-  let triples = SUPPORTED_PLATFORM_TRIPLES
-    .iter()
-    .filter_map(move |triple| {
-      let target_info = get_builtin_target_by_triple(triple).unwrap();
-      let triple = target_info.triple.as_str();
-      let res = expression.eval(|pred| match pred {
-        Predicate::Target(tp) => tp.matches(target_info),
-        Predicate::KeyValue { key, val } => *key == "target" && *val == triple,
-        // For now there is no other kind of matching
-        _ => false,
-      });
-
-      match res {
-        true => Some(triple),
-        false => None,
-      }
-    })
+  let triples = supported_platform_triples(custom_platforms)
+    .filter(move |triple| triple_matches_expression(&expression, triple))
     .filter(move |x| {
       allowlist
         .as_ref()
@@ -163,19 +172,17 @@ pub fn get_matching_bazel_triples<'a>(
   Ok(triples)
 }
 
-/// Returns all of the supported targets, filtered by an allowlist.
-pub fn get_enabled_targets(
-  allowlist: &'_ Option<HashSet<String>>,
-) -> impl Iterator<Item = &'static str> + '_ {
-  SUPPORTED_PLATFORM_TRIPLES
-    .iter()
-    .filter(move |x| {
-      allowlist
-        .as_ref()
-        .map(|targets| targets.contains(**x))
-        .unwrap_or(true)
-    })
-    .map(AsRef::as_ref)
+/// Returns all of the supported targets, including custom platforms, filtered by an allowlist.
+pub fn get_enabled_targets<'a>(
+  allowlist: &'a Option<HashSet<String>>,
+  custom_platforms: &'a BTreeMap<String, String>,
+) -> impl Iterator<Item = &'a str> + 'a {
+  supported_platform_triples(custom_platforms).filter(move |x| {
+    allowlist
+      .as_ref()
+      .map(|targets| targets.contains(*x))
+      .unwrap_or(true)
+  })
 }
 
 /// Returns whether or not the given path is a Bazel workspace root
@@ -344,6 +351,30 @@ pub fn cargo_bin_path() -> Utf8PathBuf {
   Utf8PathBuf::from(env::var("CARGO").unwrap_or_else(|_| SYSTEM_CARGO_BIN_PATH.to_string()))
 }
 
+/// Determines whether a cargo binary accepts unstable `-Z` flags, which requires a nightly or
+/// development toolchain, or `RUSTC_BOOTSTRAP=1`.
+pub fn cargo_accepts_unstable_flags(cargo_bin: &Utf8Path) -> bool {
+  if matches!(env::var("RUSTC_BOOTSTRAP").as_deref(), Ok("1")) {
+    return true;
+  }
+
+  match Command::new(cargo_bin).arg("-V").output() {
+    Ok(output) if output.status.success() => {
+      is_unstable_cargo_version(&String::from_utf8_lossy(&output.stdout))
+    }
+    _ => false,
+  }
+}
+
+/// Checks the output of `cargo -V`, such as `cargo 1.62.0-nightly (a44758ac8 2022-05-04)`, for an
+/// unstable release channel.
+fn is_unstable_cargo_version(version: &str) -> bool {
+  matches!(
+    version.split_whitespace().nth(1),
+    Some(version) if version.ends_with("-nightly") || version.ends_with("-dev")
+  )
+}
+
 #[cfg(test)]
 mod tests {
   use std::fs::File;
@@ -352,6 +383,21 @@ mod tests {
 
   use super::*;
 
+  #[test]
+  fn test_is_unstable_cargo_version() {
+    assert!(is_unstable_cargo_version(
+      "cargo 1.62.0-nightly (a44758ac8 2022-05-04)\n"
+    ));
+    assert!(is_unstable_cargo_version("cargo 1.63.0-dev\n"));
+    assert!(!is_unstable_cargo_version(
+      "cargo 1.61.0 (a028ae42f 2022-04-29)\n"
+    ));
+    assert!(!is_unstable_cargo_version(
+      "cargo 1.62.0-beta.3 (4751950cc 2022-05-20)\n"
+    ));
+    assert!(!is_unstable_cargo_version(""));
+  }
+
   #[test]
   fn test_collect_up_to_works_for_zero() {
     let test_items: Vec<u32> = Vec::new();
@@ -400,39 +446,39 @@ mod tests {
   #[test]
   fn detect_bazel_platforms() {
     assert_eq!(
-      is_bazel_supported_platform("cfg(not(fuchsia))"),
+      is_bazel_supported_platform("cfg(not(fuchsia))", &BTreeMap::new()),
       BazelTargetSupport::AllTargetsMatch
     );
     assert_eq!(
-      is_bazel_supported_platform("cfg(not(target_os = \"redox\"))"),
+      is_bazel_supported_platform("cfg(not(target_os = \"redox\"))", &BTreeMap::new()),
       BazelTargetSupport::AllTargetsMatch
     );
     assert_eq!(
-      is_bazel_supported_platform("cfg(unix)"),
+      is_bazel_supported_platform("cfg(unix)", &BTreeMap::new()),
       BazelTargetSupport::SpecificTargetMatches
     );
     assert_eq!(
-      is_bazel_supported_platform("cfg(not(windows))"),
+      is_bazel_supported_platform("cfg(not(windows))", &BTreeMap::new()),
       BazelTargetSupport::SpecificTargetMatches
     );
     assert_eq!(
-      is_bazel_supported_platform("cfg(target = \"x86_64-apple-darwin\")"),
+      is_bazel_supported_platform("cfg(target = \"x86_64-apple-darwin\")", &BTreeMap::new()),
       BazelTargetSupport::SpecificTargetMatches
     );
     assert_eq!(
-      is_bazel_supported_platform("x86_64-apple-darwin"),
+      is_bazel_supported_platform("x86_64-apple-darwin", &BTreeMap::new()),
       BazelTargetSupport::SpecificTargetMatches
     );
     assert_eq!(
-      is_bazel_supported_platform("unknown-unknown-unknown"),
+      is_bazel_supported_platform("unknown-unknown-unknown", &BTreeMap::new()),
       BazelTargetSupport::Unsupported
     );
     assert_eq!(
-      is_bazel_supported_platform("cfg(foo)"),
+      is_bazel_supported_platform("cfg(foo)", &BTreeMap::new()),
       BazelTargetSupport::Unsupported
     );
     assert_eq!(
-      is_bazel_supported_platform("cfg(target_os = \"redox\")"),
+      is_bazel_supported_platform("cfg(target_os = \"redox\")", &BTreeMap::new()),
       BazelTargetSupport::Unsupported
     );
   }
@@ -443,4 +489,51 @@ mod tests {
       get_builtin_target_by_triple(triple).unwrap();
     }
   }
+
+  #[test]
+  fn detect_custom_bazel_platforms() {
+    let custom_platforms: BTreeMap<String, String> = vec![
+      (
+        "riscv64gc-unknown-linux-gnu".to_owned(),
+        "//platforms:riscv64".to_owned(),
+      ),
+      (
+        "thumbv7em-custom-board".to_owned(),
+        "@board//:thumbv7em".to_owned(),
+      ),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(
+      is_bazel_supported_platform("riscv64gc-unknown-linux-gnu", &custom_platforms),
+      BazelTargetSupport::SpecificTargetMatches
+    );
+    assert_eq!(
+      is_bazel_supported_platform("riscv64gc-unknown-linux-gnu", &BTreeMap::new()),
+      BazelTargetSupport::Unsupported
+    );
+    assert_eq!(
+      is_bazel_supported_platform("thumbv7em-custom-board", &custom_platforms),
+      BazelTargetSupport::SpecificTargetMatches
+    );
+
+    // Built-in triples are matched by their cfg attributes, unknown ones only by name
+    let linux_triples: Vec<&str> =
+      get_matching_bazel_triples("cfg(target_os = \"linux\")", &None, &custom_platforms)
+        .unwrap()
+        .collect();
+    assert!(linux_triples.contains(&"riscv64gc-unknown-linux-gnu"));
+    assert!(!linux_triples.contains(&"thumbv7em-custom-board"));
+
+    let allowlist = Some(
+      vec!["riscv64gc-unknown-linux-gnu".to_owned()]
+        .into_iter()
+        .collect(),
+    );
+    assert_eq!(
+      get_enabled_targets(&allowlist, &custom_platforms).collect::<Vec<&str>>(),
+      vec!["riscv64gc-unknown-linux-gnu"]
+    );
+  }
 }
@@ -21,16 +21,19 @@ _DEPENDENCIES = {
         "cargo_toml": "@cargo_raze__cargo_toml__0_8_1//:cargo_toml",
         "cfg-expr": "@cargo_raze__cfg_expr__0_10_2//:cfg_expr",
         "crates-index": "@cargo_raze__crates_index__0_17_0//:crates_index",
+        "diff": "@cargo_raze__diff__0_1_12//:diff",
         "docopt": "@cargo_raze__docopt__1_1_1//:docopt",
         "glob": "@cargo_raze__glob__0_3_0//:glob",
         "itertools": "@cargo_raze__itertools__0_10_0//:itertools",
         "log": "@cargo_raze__log__0_4_14//:log",
         "pathdiff": "@cargo_raze__pathdiff__0_2_0//:pathdiff",
         "regex": "@cargo_raze__regex__1_5_5//:regex",
+        "reqwest": "@cargo_raze__reqwest__0_11_2//:reqwest",
         "rustc-serialize": "@cargo_raze__rustc_serialize__0_3_24//:rustc_serialize",
         "semver": "@cargo_raze__semver__1_0_3//:semver",
         "serde": "@cargo_raze__serde__1_0_126//:serde",
         "serde_json": "@cargo_raze__serde_json__1_0_64//:serde_json",
+        "sha2": "@cargo_raze__sha2__0_8_2//:sha2",
         "slug": "@cargo_raze__slug__0_1_4//:slug",
         "spdx": "@cargo_raze__spdx__0_3_6//:spdx",
         "tempfile": "@cargo_raze__tempfile__3_2_0//:tempfile",
@@ -2291,6 +2294,16 @@ def cargo_raze_fetch_remote_crates():
         build_file = Label("//third_party/cargo/remote:BUILD.sha-1-0.8.2.bazel"),
     )
 
+    maybe(
+        http_archive,
+        name = "cargo_raze__sha2__0_8_2",
+        url = "https://crates.io/api/v1/crates/sha2/0.8.2/download",
+        type = "tar.gz",
+        sha256 = "a256f46ea78a0c0d9ff00077504903ac881a1dafdc20da66545699e7776b3e69",
+        strip_prefix = "sha2-0.8.2",
+        build_file = Label("//third_party/cargo/remote:BUILD.sha2-0.8.2.bazel"),
+    )
+
     maybe(
         http_archive,
         name = "cargo_raze__signal_hook__0_3_8",